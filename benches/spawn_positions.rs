@@ -0,0 +1,36 @@
+//! How long does it take to find the start and goal rooms as mazes grow?
+//!
+//! Run with `cargo bench --bench spawn_positions`.
+
+use browser_maze_bevy::maze::{
+    choose_spawn_positions, choose_walls, Algorithm, MazeSpec, Topology,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+fn spawn_positions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("choose_spawn_positions");
+    for halflength in [10, 50, 100, 250] {
+        let graph = choose_walls(&MazeSpec {
+            topology: Topology::Square,
+            x_range: -halflength..=halflength,
+            z_range: -halflength..=halflength,
+            mask: None,
+            floors: 1,
+            algorithm: Algorithm::default(),
+            braid: 0.0,
+            seed: 0xaaaaaaaa,
+        })
+        .graph;
+        let side = halflength * 2;
+        group.throughput(Throughput::Elements(graph.node_count() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{side}x{side}")),
+            &graph,
+            |b, graph| b.iter(|| choose_spawn_positions(graph)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, spawn_positions);
+criterion_main!(benches);
@@ -15,7 +15,8 @@ fi
 
 cargo build \
     --release \
-    --target wasm32-unknown-unknown
+    --target wasm32-unknown-unknown \
+    --bin browser-maze-bevy
 
 wasm-bindgen \
     --out-name browser_maze_bevy \
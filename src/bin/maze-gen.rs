@@ -0,0 +1,288 @@
+//! Generates mazes without starting the game, for building level packs and
+//! checking the generator's output.
+//!
+//! Run `maze-gen --help` for the options.
+
+use std::{fs, io::Write, path::Path, process::ExitCode};
+
+use browser_maze_bevy::maze::{
+    Algorithm, Difficulty, ExportOptions, Mask, MazeAlgorithm, MazeLayout, MazeSpec, Topology,
+    MAX_PNG_PIXELS,
+};
+use rand::{rngs::OsRng, RngCore};
+
+const USAGE: &str = "\
+Usage: maze-gen [OPTIONS]
+
+Generates a maze and writes it to stdout, or to a file.
+
+Options:
+  --width ROOMS        Rooms across (default 20; polar mazes ignore this)
+  --height ROOMS       Rooms from north to south, or rings for polar mazes
+                       (default 20)
+  --topology NAME      square, hex or polar (default square)
+  --algorithm NAME     growing-tree, recursive-backtracker, kruskal, prim,
+                       wilson, aldous-broder, eller, hunt-and-kill,
+                       sidewinder, binary-tree or recursive-division
+                       (default growing-tree)
+  --braid FRACTION     Fraction of dead ends to remove, from 0 to 1
+                       (default 0)
+  --floors COUNT       Floors to stack (default 1)
+  --mask FILE          ASCII or PNG picture of the shape to cut the maze into
+  --seed HEX           Seed, in hex with an optional 0x prefix (default
+                       random)
+  --difficulty NAME    easy, medium or hard: try nearby seeds, and then other
+                       algorithms, until the maze is about that hard
+  --metrics            Report how hard the maze is on stderr
+  --format NAME        json, ron, ascii, svg or png (default: from the output
+                       file's extension, or json)
+  --solution           Draw the way from start to goal (svg and png only)
+  --scale PIXELS       Pixels per room (svg and png only; default 24)
+  -o, --output FILE    Where to write the maze (default stdout)
+  -h, --help           Show this message
+";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Json,
+    Ron,
+    Ascii,
+    Svg,
+    Png,
+}
+
+impl Format {
+    const ALL: [(&'static str, Format); 5] = [
+        ("json", Format::Json),
+        ("ron", Format::Ron),
+        ("ascii", Format::Ascii),
+        ("svg", Format::Svg),
+        ("png", Format::Png),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, format)| *format)
+    }
+
+    /// Guesses the format from a file's extension.  `.txt` files get the
+    /// ASCII drawing.
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "txt" => Some(Format::Ascii),
+            extension => Self::from_name(extension),
+        }
+    }
+}
+
+/// Everything the command line asked for.
+struct Options {
+    spec: MazeSpec,
+    format: Format,
+    export: ExportOptions,
+    difficulty: Option<Difficulty>,
+    metrics: bool,
+    output: Option<String>,
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("maze-gen: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("maze-gen: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut width = 20;
+    let mut height = 20;
+    let mut topology = Topology::default();
+    let mut algorithm = Algorithm::default();
+    let mut braid = 0.0;
+    let mut floors = 1;
+    let mut mask = None;
+    let mut seed = None;
+    let mut format = None;
+    let mut export = ExportOptions::default();
+    let mut difficulty = None;
+    let mut metrics = false;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--solution" {
+            export.show_solution = true;
+            continue;
+        }
+        if arg == "--metrics" {
+            metrics = true;
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        let number = |what: &str| {
+            value
+                .parse::<i32>()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| format!("{what} should be a positive whole number, not {value:?}"))
+        };
+        match arg.as_str() {
+            "--width" => width = number("the width")?,
+            "--height" => height = number("the height")?,
+            "--floors" => floors = number("the number of floors")?,
+            "--topology" => {
+                topology = Topology::ALL
+                    .into_iter()
+                    .find(|topology| slug(topology.name()) == *value)
+                    .ok_or_else(|| format!("there's no {value:?} topology"))?;
+            }
+            "--algorithm" => {
+                algorithm = Algorithm::ALL
+                    .into_iter()
+                    .find(|algorithm| slug(algorithm.name()) == *value)
+                    .ok_or_else(|| format!("there's no {value:?} algorithm"))?;
+            }
+            "--braid" => {
+                braid = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|braid| (0.0..=1.0).contains(braid))
+                    .ok_or_else(|| format!("the braid should be from 0 to 1, not {value:?}"))?;
+            }
+            "--mask" => {
+                let bytes =
+                    fs::read(value).map_err(|err| format!("couldn't read {value}: {err}"))?;
+                mask = Some(Mask::parse(&bytes).map_err(|err| format!("{value}: {err}"))?);
+            }
+            "--seed" => {
+                seed = Some(
+                    u64::from_str_radix(value.trim_start_matches("0x"), 16)
+                        .map_err(|_| format!("the seed should be in hex, not {value:?}"))?,
+                );
+            }
+            "--difficulty" => {
+                difficulty = Some(
+                    Difficulty::ALL
+                        .into_iter()
+                        .find(|difficulty| slug(difficulty.name()) == *value)
+                        .ok_or_else(|| format!("there's no {value:?} difficulty"))?,
+                );
+            }
+            "--format" => {
+                format = Some(
+                    Format::from_name(value)
+                        .ok_or_else(|| format!("there's no {value:?} format"))?,
+                );
+            }
+            "--scale" => {
+                export.pixels_per_room = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|scale| *scale > 0.0)
+                    .ok_or_else(|| {
+                        format!("the scale should be a positive number, not {value:?}")
+                    })?;
+            }
+            "-o" | "--output" => output = Some(value.clone()),
+            _ => return Err(format!("unknown option {arg:?}")),
+        }
+    }
+
+    // Center the grid like the game does.  Polar mazes only care about how
+    // many rings there are.
+    let centered = |rooms: i32| -(rooms / 2)..=rooms - rooms / 2;
+    let spec = MazeSpec {
+        topology,
+        x_range: centered(width),
+        z_range: match topology {
+            Topology::Polar => 0..=height,
+            _ => centered(height),
+        },
+        mask,
+        floors,
+        algorithm,
+        braid,
+        seed: seed.unwrap_or_else(|| OsRng.next_u64()),
+    };
+    if !algorithm.supports(&spec) {
+        return Err(format!(
+            "{} can't generate {}{} mazes",
+            algorithm.name(),
+            if spec.mask.is_some() { "masked " } else { "" },
+            topology.name().to_lowercase(),
+        ));
+    }
+    let format = format
+        .or_else(|| Format::from_path(Path::new(output.as_ref()?)))
+        .unwrap_or(Format::Json);
+    Ok(Options {
+        spec,
+        format,
+        export,
+        difficulty,
+        metrics,
+        output,
+    })
+}
+
+/// How names are written on the command line: lowercase, with dashes for
+/// spaces.
+fn slug(name: &str) -> String {
+    name.to_lowercase().replace(' ', "-")
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let layout = match options.difficulty {
+        Some(difficulty) => MazeLayout::generate_with_difficulty(&options.spec, difficulty),
+        None => MazeLayout::generate(&options.spec),
+    }
+    .map_err(|err| format!("couldn't generate the maze: {err}"))?;
+    if options.metrics {
+        // Aiming for a difficulty can change the seed and the algorithm.
+        eprintln!(
+            "Seed: {:x}\nAlgorithm: {}",
+            layout.spec.seed,
+            layout.spec.algorithm.name()
+        );
+        match layout.metrics() {
+            Some(metrics) => eprintln!("{metrics}"),
+            None => eprintln!("There's no way from the start to the goal"),
+        }
+    }
+    let contents = match options.format {
+        Format::Json => layout.to_json().into_bytes(),
+        Format::Ron => layout.to_ron().into_bytes(),
+        Format::Ascii => layout
+            .to_ascii()
+            .ok_or("only square mazes can be drawn in ASCII")?
+            .into_bytes(),
+        Format::Svg => layout.to_svg(&options.export).into_bytes(),
+        Format::Png => layout.to_png(&options.export).ok_or_else(|| {
+            format!("the PNG would be over {MAX_PNG_PIXELS} pixels; try a smaller --scale")
+        })?,
+    };
+    match &options.output {
+        Some(path) => {
+            fs::write(path, contents).map_err(|err| format!("couldn't write {path}: {err}"))
+        }
+        None => std::io::stdout()
+            .write_all(&contents)
+            .map_err(|err| format!("couldn't write the maze: {err}")),
+    }
+}
@@ -0,0 +1,59 @@
+//! The campaign: one generated maze after another, each a little bigger than
+//! the last, with the player's progress kept between sessions.
+
+use bevy::prelude::*;
+use browser_maze_bevy::maze::Algorithm;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+/// Where the player's progress is stored.
+const PROGRESS: &str = "campaign.json";
+
+/// Rooms per half-side of the first maze in the campaign.
+const FIRST_HALFLENGTH: i32 = 2;
+
+/// Rooms per half-side of the biggest maze in the campaign.  Mazes stop
+/// growing there, but keep changing algorithms.
+const LAST_HALFLENGTH: i32 = 25;
+
+/// How big the campaign's `stage`th maze (counting from 0) is, as rooms per
+/// half-side.
+pub fn side_halflength(stage: usize) -> i32 {
+    let grown = i32::try_from(stage).unwrap_or(i32::MAX);
+    FIRST_HALFLENGTH.saturating_add(grown).min(LAST_HALFLENGTH)
+}
+
+/// Which algorithm carves the campaign's `stage`th maze.  They take turns,
+/// so no two mazes in a row feel the same.
+pub fn algorithm(stage: usize) -> Algorithm {
+    Algorithm::ALL[stage % Algorithm::ALL.len()]
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, Resource)]
+pub struct CampaignProgress {
+    /// How many of the campaign's mazes the player has solved, which is also
+    /// the stage to pick up from.
+    pub solved: usize,
+}
+
+impl CampaignProgress {
+    /// Reads the stored progress.  Missing or unreadable progress starts the
+    /// player from the beginning.
+    pub fn load() -> Self {
+        let Some(text) = storage::load(PROGRESS) else {
+            return CampaignProgress::default();
+        };
+        serde_json::from_str(&text).unwrap_or_else(|err| {
+            warn!("Couldn't read the campaign progress from {PROGRESS}: {err}");
+            CampaignProgress::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let text = serde_json::to_string(self).expect("progress should serialize");
+        if let Err(err) = storage::save(PROGRESS, &text) {
+            warn!("Couldn't save the campaign progress to {PROGRESS}: {err}");
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! The parts of the game that don't depend on Bevy, so that benchmarks and
+//! tools can use them without starting an app.
+
+pub mod maze;
+pub mod recording;
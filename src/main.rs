@@ -1,38 +1,161 @@
-use std::f32::consts::TAU;
+use std::{f32::consts::TAU, ffi::OsStr, ops::RangeInclusive, path::Path};
 
 use bevy::{
+    app::AppExit,
     input::mouse::MouseMotion,
     prelude::*,
     render::camera::ScalingMode,
-    window::{close_on_esc, CursorGrabMode},
+    utils::HashSet,
+    window::{CursorGrabMode, FileDragAndDrop},
 };
 
-mod maze;
+mod campaign;
+mod records;
+mod spawn;
+mod storage;
 
 use bevy_rapier3d::prelude::*;
-use maze::{generate_walls, Sizes};
+use browser_maze_bevy::{
+    maze::{
+        Algorithm, Difficulty, ExportOptions, Level, Mask, MazeAlgorithm, MazeLayout, MazeSpec,
+        Room, Topology,
+    },
+    recording::{self, Ghost, GhostFrame, InputFrame, InputReplay, TICKS_PER_SECOND},
+};
+use campaign::CampaignProgress;
+use rand::{rngs::OsRng, RngCore};
+use records::{BestTimes, RecordKey};
+use spawn::{Chunk, ChunkedMaze, Cullable, Sizes, WallBatching};
 
 /// How many rooms per half-side of the maze?
 const SIDE_HALFLENGTH: i32 = 10;
 
+/// How much of the maze the map shows at once, as rooms per half-side.
+/// Smaller mazes are shown whole.
+const MAP_HALFLENGTH: i32 = 10;
+
+/// The maze sizes to choose between, as rooms per half-side.
+const SIDE_HALFLENGTHS: [i32; 3] = [SIDE_HALFLENGTH, 50, 250];
+
+/// How far from the avatar (in world units, along `x` and `z`) the maze is
+/// spawned.  This has to cover everything the map can show.
+const STREAM_DISTANCE: f32 = 64.0;
+
+/// How many passages away from the avatar's room to look for rooms in sight.
+/// Anything much farther is outside `STREAM_DISTANCE` anyway.
+const SIGHT_PASSAGES: usize = 32;
+
+/// How far from the avatar spawned parts of the maze can get before they're
+/// despawned.  The gap keeps chunks at the edge from flickering in and out.
+const UNLOAD_DISTANCE: f32 = 96.0;
+
 /// How big is each room?
 const ROOM_SIDE_LENGTH: f32 = 2.0;
 
+const SIZES: Sizes = Sizes {
+    room_side_length: ROOM_SIDE_LENGTH,
+    wall_radius: 0.1,
+    room_height: 0.9,
+    floor_thickness: 0.1,
+    ramp_width: ROOM_SIDE_LENGTH / 2.0,
+};
+
+/// How many floors can the maze have?
+const MAX_FLOORS: i32 = 3;
+
+/// Shapes to cut the maze into, besides the full grid.  Like the font,
+/// they're baked into the binary.
+const MASKS: [(&str, &[u8]); 3] = [
+    ("Heart", include_bytes!("../assets/masks/heart.txt")),
+    ("Ring", include_bytes!("../assets/masks/ring.txt")),
+    ("Star", include_bytes!("../assets/masks/star.png")),
+];
+
+/// Hand-drawn mazes to play instead of generated ones, in order.  Like the
+/// masks, they're baked into the binary.
+const LEVELS: [(&str, &str); 3] = [
+    (
+        "First steps",
+        include_str!("../assets/levels/first-steps.txt"),
+    ),
+    ("Spiral", include_str!("../assets/levels/spiral.txt")),
+    ("Courtyard", include_str!("../assets/levels/courtyard.txt")),
+];
+
+/// How big is the avatar's collider?
+const AVATAR_HALF_HEIGHT: f32 = 0.3;
+const AVATAR_RADIUS: f32 = 0.4;
+
+/// How fast does the avatar fall when there's nothing under it (units/sec)?
+const FALL_SPEED: f32 = 4.0;
+
 const MOUSE_SENSITIVITY: f32 = 0.5;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+        .add_state::<GameState>()
         .add_startup_system(setup)
         .add_system(reset_maze.run_if(resource_exists_and_equals(MazeNeedsReset(true))))
-        .add_system(close_on_esc)
-        .add_system(map_user_input)
-        .add_system(move_avatars.in_schedule(CoreSchedule::FixedUpdate))
-        .add_system(check_goal)
+        .add_system(
+            stream_chunks
+                .before(reset_maze)
+                .run_if(resource_exists_and_equals(MazeNeedsReset(false))),
+        )
+        .add_system(follow_avatar_on_map)
+        .add_system(cull_out_of_sight)
+        .add_system(
+            show_maze_info
+                .run_if(resource_changed::<MazeSeed>().or_else(resource_changed::<MazeSettings>())),
+        )
+        .add_system(show_render_stats)
+        .add_system(show_run_clock)
+        .add_system(load_dropped_mazes)
+        .add_system(save_maze)
+        .add_systems(
+            (map_user_input, select_map_floor, check_goal).in_set(OnUpdate(GameState::Playing)),
+        )
+        .add_system(pause_game.run_if(in_run))
+        .add_system(show_map_floor)
+        .add_systems(
+            (
+                feed_replay.run_if(in_state(GameState::Replaying)),
+                move_avatars,
+                record_inputs,
+                tick_run_clock,
+                record_ghost,
+                replay_ghost,
+            )
+                .chain()
+                .distributive_run_if(in_run)
+                // Nothing moves until a requested maze is in place, so a
+                // replay starts on the same tick its run did.
+                .distributive_run_if(maze_in_place)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        )
         .add_system(switch_camera)
-        .insert_resource(FixedTime::new_from_secs(1.0 / 60.0))
-        .run();
+        .add_system(release_mouse.in_schedule(OnExit(GameState::Playing)))
+        .add_system(start_game.in_set(OnUpdate(GameState::Title)))
+        .add_system(use_pause_menu.in_set(OnUpdate(GameState::Paused)))
+        .add_system(forget_replay_verdict.in_schedule(OnExit(GameState::Paused)))
+        .add_system(finish_level.in_set(OnUpdate(GameState::LevelComplete)))
+        .add_system(advance_campaign.in_schedule(OnEnter(GameState::LevelComplete)))
+        .add_system(
+            keep_best_run
+                .before(show_screen)
+                .in_schedule(OnEnter(GameState::LevelComplete)),
+        )
+        .insert_resource(FixedTime::new_from_secs(1.0 / TICKS_PER_SECOND as f32));
+    for state in [
+        GameState::Title,
+        GameState::Paused,
+        GameState::LevelComplete,
+    ] {
+        app.add_system(show_screen.in_schedule(OnEnter(state)))
+            .add_system(hide_screen.in_schedule(OnExit(state)));
+    }
+    app.run();
 }
 
 #[derive(Copy, Clone, Component)]
@@ -56,6 +179,252 @@ pub struct AvatarPitch {
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Resource)]
 struct MazeNeedsReset(bool);
 
+/// Whether the maze asked for last has been spawned.
+fn maze_in_place(reset_request: Option<Res<MazeNeedsReset>>) -> bool {
+    reset_request.is_some_and(|reset_request| !reset_request.0)
+}
+
+/// Where the player is in the game.  The avatar only moves while `Playing`;
+/// every other state shows a screen over the maze.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, States)]
+enum GameState {
+    #[default]
+    Title,
+    Playing,
+    Paused,
+    /// The avatar reached the goal.  The next maze waits until the player
+    /// has seen how it went.
+    LevelComplete,
+    /// The avatar moves as a recorded run tells it to, not as the player
+    /// does.
+    Replaying,
+}
+
+/// Whether the avatar is on a run through the maze, whether it's the
+/// player's or a replay's.
+fn in_run(state: Res<State<GameState>>) -> bool {
+    matches!(state.0, GameState::Playing | GameState::Replaying)
+}
+
+/// The screen shown over the maze outside the `Playing` state.
+#[derive(Component)]
+struct Screen;
+
+/// The font for all the text on screen.
+#[derive(Resource)]
+struct UiFont(Handle<Font>);
+
+/// How long the player has been playing the current maze, in `FixedUpdate`
+/// ticks.  It waits for the avatar's first move to start, and stops at the
+/// goal.  Counting ticks rather than frame time makes a run take exactly as
+/// long every time it's replayed.
+#[derive(Default, Resource)]
+struct RunClock {
+    ticks: u32,
+    running: bool,
+}
+
+impl RunClock {
+    /// Whether the clock has run and stopped at the goal.
+    fn finished(&self) -> bool {
+        !self.running && self.ticks > 0
+    }
+}
+
+#[derive(Component)]
+struct RunClockText;
+
+/// The avatar's path through the current run so far.
+#[derive(Default, Resource)]
+struct GhostRecorder(Ghost);
+
+/// The player's input on every tick of the current run so far.
+#[derive(Default, Resource)]
+struct InputRecorder(Vec<InputFrame>);
+
+/// The inputs of the run being replayed.
+#[derive(Resource)]
+struct ReplayPlayer {
+    frames: Vec<InputFrame>,
+    /// How many ticks into `frames` it's got.
+    tick: usize,
+}
+
+/// The key to keep the current maze's best time under, if it gets one.
+#[derive(Default, Resource)]
+struct CurrentRecordKey(Option<RecordKey>);
+
+/// How the last replay compared with the run it recorded, to show on the
+/// pause screen it stops at.
+#[derive(Default, Resource)]
+struct ReplayVerdict(Option<String>);
+
+/// Follows the path of the best run through the current maze.
+#[derive(Component)]
+struct GhostAvatar {
+    ghost: Ghost,
+    /// How many ticks into `ghost` it's got.
+    tick: usize,
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Resource)]
+struct MazeSeed {
+    /// The seed used to generate the current maze.
+    seed: u64,
+    /// If set, resetting the maze reuses `seed` instead of drawing a new one.
+    pinned: bool,
+}
+
+impl MazeSeed {
+    /// Reads a pinned seed from the `MAZE_SEED` environment variable (hex with
+    /// an optional `0x` prefix), falling back to an unpinned random seed.
+    fn from_env() -> Self {
+        let pinned_seed = std::env::var("MAZE_SEED")
+            .ok()
+            .and_then(|value| u64::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok());
+        match pinned_seed {
+            Some(seed) => MazeSeed { seed, pinned: true },
+            None => MazeSeed {
+                seed: 0,
+                pinned: false,
+            },
+        }
+    }
+}
+
+/// A maze loaded from a file or a replay, rather than generated from
+/// `MazeSettings`.
+#[derive(Default, Resource)]
+struct LoadedLayout {
+    /// The maze to spawn on the next reset, instead of generating one.
+    next: Option<MazeLayout>,
+    /// The loaded maze in play, and the settings it was loaded with.
+    /// Settings can't describe every size a saved maze might be, so resets
+    /// generate its spec instead, until the settings change.  Hand-drawn
+    /// levels can't be generated again from their spec, so resets spawn
+    /// them as they are.
+    current: Option<(MazeLayout, MazeSettings)>,
+}
+
+impl LoadedLayout {
+    /// Whether the maze in play is a hand-drawn level that was loaded.
+    fn playing_level(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|(layout, _)| layout.hand_drawn)
+    }
+}
+
+/// Everything besides the seed that decides what the next maze looks like.
+#[derive(Copy, Clone, Debug, PartialEq, Resource)]
+struct MazeSettings {
+    topology: Topology,
+    algorithm: Algorithm,
+    /// The fraction of dead ends to remove; see `MazeSpec::braid`.
+    braid: f32,
+    floors: i32,
+    /// Rooms per half-side of the maze.
+    side_halflength: i32,
+    /// Which of `MASKS` to shape the maze with, if any.
+    mask: Option<usize>,
+    /// Which of `LEVELS` to play instead of generating a maze, if any.
+    level: Option<usize>,
+    /// Which stage of the campaign this is, if it's a campaign maze.
+    campaign: Option<usize>,
+    /// How hard to make generated mazes, if it matters.
+    difficulty: Option<Difficulty>,
+    walls: WallBatching,
+}
+
+impl Default for MazeSettings {
+    fn default() -> Self {
+        MazeSettings {
+            topology: Topology::default(),
+            algorithm: Algorithm::default(),
+            braid: 0.0,
+            floors: 1,
+            side_halflength: SIDE_HALFLENGTH,
+            mask: None,
+            level: None,
+            campaign: None,
+            difficulty: None,
+            walls: WallBatching::default(),
+        }
+    }
+}
+
+impl MazeSettings {
+    fn spec(&self, seed: u64) -> MazeSpec {
+        // A polar maze is centered on the origin already, so its radius
+        // matches the other grids' half-width.
+        let half = self.side_halflength;
+        let z_range = match self.topology {
+            Topology::Polar => 0..=half,
+            _ => -half..=half,
+        };
+        MazeSpec {
+            topology: self.topology,
+            x_range: -half..=half,
+            z_range,
+            mask: self
+                .mask
+                .map(|index| Mask::parse(MASKS[index].1).expect("built-in masks should parse")),
+            floors: self.floors,
+            algorithm: self.algorithm,
+            braid: self.braid,
+            seed,
+        }
+    }
+
+    /// These settings, changed to generate mazes like `spec` as closely as
+    /// they can.  Masks other than the built-in ones can't be matched, and
+    /// neither can sizes other than an even number of rooms centered on the
+    /// origin.
+    fn matching(&self, spec: &MazeSpec) -> Self {
+        let span = |range: &RangeInclusive<i32>| range.end() - range.start();
+        MazeSettings {
+            topology: spec.topology,
+            algorithm: spec.algorithm,
+            braid: spec.braid,
+            floors: spec.floors,
+            side_halflength: match spec.topology {
+                Topology::Polar => span(&spec.z_range),
+                _ => span(&spec.x_range).max(span(&spec.z_range)) / 2,
+            },
+            mask: MASKS
+                .iter()
+                .position(|(_, bytes)| Mask::parse(bytes).ok() == spec.mask),
+            level: None,
+            campaign: None,
+            difficulty: None,
+            walls: self.walls,
+        }
+    }
+
+    /// These settings, changed to generate the campaign's `stage`th maze.
+    fn for_campaign(&self, stage: usize) -> Self {
+        let mut settings = MazeSettings {
+            side_halflength: campaign::side_halflength(stage),
+            algorithm: campaign::algorithm(stage),
+            level: None,
+            campaign: Some(stage),
+            ..*self
+        };
+        while !settings.algorithm.supports(&settings.spec(0)) {
+            settings.algorithm = settings.algorithm.next();
+        }
+        settings
+    }
+
+    fn mask_name(&self) -> &'static str {
+        self.mask.map_or("Full", |index| MASKS[index].0)
+    }
+
+    fn difficulty_name(&self) -> &'static str {
+        self.difficulty.map_or("Any", Difficulty::name)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 enum ViewMode {
     FirstPerson,
@@ -68,16 +437,29 @@ struct MazeRoot;
 #[derive(Component)]
 struct RestrictToView(ViewMode);
 
+#[derive(Component)]
+struct MazeInfoText;
+
+/// The section of the `MazeInfoText` that `show_render_stats` fills in.
+const RENDER_STATS_SECTION: usize = 1;
+
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Resource)]
 struct CurrentView(ViewMode);
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Resource)]
 struct MouseGrabbed(bool);
 
+/// Which floor the map shows.  Floors above it are hidden while the map is
+/// up.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Resource)]
+struct MapFloor(i32);
+
 fn setup(
     mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut fonts: ResMut<Assets<Font>>,
 ) {
     // Player
     commands
@@ -91,15 +473,12 @@ fn setup(
             SpatialBundle::default(),
         ))
         .with_children(|children| {
-            children.spawn(PbrBundle {
-                mesh: meshes.add(shape::RegularPolygon::new(0.5, 3).into()),
-                material: materials.add(Color::BLUE.into()),
-                transform: Transform::from_rotation(
-                    Quat::from_rotation_y(TAU / 6.0) * Quat::from_rotation_x(-TAU / 4.0),
-                )
-                .with_translation(Vec3::Y * 0.1),
-                ..default()
-            });
+            children.spawn(avatar_marker(
+                &mut meshes,
+                materials.add(Color::BLUE.into()),
+            ));
+            // The light travels with the avatar, so it lights the same few
+            // rooms around it however big the maze is.
             children.spawn(PointLightBundle {
                 point_light: PointLight {
                     intensity: 450.0,
@@ -118,22 +497,14 @@ fn setup(
             ));
         });
 
-    // Floor
-    commands.spawn(PbrBundle {
-        mesh: meshes
-            .add(shape::Plane::from_size(SIDE_HALFLENGTH as f32 * 2.0 * ROOM_SIDE_LENGTH).into()),
-        material: materials.add(Color::SILVER.into()),
-        ..default()
-    });
-
     // Cameras
     commands.spawn((
         RestrictToView(ViewMode::Map),
         Camera3dBundle {
             projection: Projection::Orthographic(OrthographicProjection {
                 scaling_mode: ScalingMode::AutoMin {
-                    min_width: SIDE_HALFLENGTH as f32 * 2.0 * ROOM_SIDE_LENGTH,
-                    min_height: SIDE_HALFLENGTH as f32 * 2.0 * ROOM_SIDE_LENGTH,
+                    min_width: MAP_HALFLENGTH as f32 * 2.0 * ROOM_SIDE_LENGTH,
+                    min_height: MAP_HALFLENGTH as f32 * 2.0 * ROOM_SIDE_LENGTH,
                 },
                 scale: 1.0,
                 ..default()
@@ -143,18 +514,119 @@ fn setup(
         },
     ));
 
+    // HUD
+    // The font is baked into the binary so the WASM build doesn't need to
+    // serve an assets directory.
+    let font = fonts.add(
+        Font::try_from_bytes(include_bytes!("../assets/fonts/DejaVuSansMono.ttf").to_vec())
+            .unwrap(),
+    );
+    commands.insert_resource(UiFont(font.clone()));
+    commands.spawn((
+        MazeInfoText,
+        TextBundle::from_sections([0, RENDER_STATS_SECTION].map(|_| {
+            TextSection::from_style(TextStyle {
+                font: font.clone(),
+                font_size: 18.0,
+                color: Color::WHITE,
+            })
+        }))
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(8.0),
+                left: Val::Px(8.0),
+                ..default()
+            },
+            ..default()
+        }),
+    ));
+
+    commands.spawn((
+        RunClockText,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: 24.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_text_alignment(TextAlignment::Right)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(8.0),
+                right: Val::Px(8.0),
+                ..default()
+            },
+            ..default()
+        }),
+    ));
+
     // UI settings
     commands.insert_resource(CurrentView(ViewMode::FirstPerson));
     commands.insert_resource(MouseGrabbed(false));
+    commands.insert_resource(MapFloor(0));
+
+    // Maze settings
+    commands.insert_resource(MazeSeed::from_env());
+    commands.insert_resource(MazeSettings::default());
+    commands.insert_resource(LoadedLayout::default());
+    commands.insert_resource(RunClock::default());
+    commands.insert_resource(BestTimes::load());
+    commands.insert_resource(CurrentRecordKey::default());
+    commands.insert_resource(CampaignProgress::load());
+    commands.insert_resource(GhostRecorder::default());
+    commands.insert_resource(InputRecorder::default());
+    commands.insert_resource(ReplayVerdict::default());
 
     // Request the first maze
     commands.insert_resource(MazeNeedsReset(true));
+
+    // Or replay a run from a file
+    if let Ok(path) = std::env::var("MAZE_REPLAY") {
+        match read_replay(Path::new(&path)) {
+            Ok(replay) => start_replay(&mut commands, &mut next_state, replay),
+            Err(err) => warn!("Couldn't load the replay in {path}: {err}"),
+        }
+    }
+}
+
+fn read_replay(path: &Path) -> Result<InputReplay, String> {
+    let bytes = std::fs::read(path).map_err(|err| err.to_string())?;
+    InputReplay::from_bytes(&bytes).map_err(|err| err.to_string())
+}
+
+/// Starts replaying `replay`, from the start of a fresh copy of its maze.
+fn start_replay(
+    commands: &mut Commands,
+    next_state: &mut NextState<GameState>,
+    replay: InputReplay,
+) {
+    commands.insert_resource(LoadedLayout {
+        next: Some(replay.layout),
+        ..default()
+    });
+    commands.insert_resource(MazeNeedsReset(true));
+    commands.insert_resource(ReplayPlayer {
+        frames: replay.frames,
+        tick: 0,
+    });
+    next_state.set(GameState::Replaying);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn reset_maze(
     mut commands: Commands,
     old_mazes: Query<Entity, With<MazeRoot>>,
     mut reset_request: ResMut<MazeNeedsReset>,
+    mut maze_seed: ResMut<MazeSeed>,
+    mut settings: ResMut<MazeSettings>,
+    mut loaded: ResMut<LoadedLayout>,
+    mut clock: ResMut<RunClock>,
+    mut recorder: ResMut<GhostRecorder>,
+    mut inputs: ResMut<InputRecorder>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut avatars: Query<&mut Transform, With<Avatar>>,
@@ -162,44 +634,148 @@ fn reset_maze(
     for m in &old_mazes {
         commands.entity(m).despawn_recursive();
     }
+    *clock = RunClock::default();
+    recorder.0.frames.clear();
+    inputs.0.clear();
 
     *reset_request = MazeNeedsReset(false);
 
+    // Changing any setting moves on from a loaded maze.
+    if loaded
+        .current
+        .as_ref()
+        .is_some_and(|(_, loaded_with)| loaded_with != &*settings)
+    {
+        loaded.current = None;
+    }
+    let layout = match (loaded.next.take(), settings.level) {
+        (Some(layout), _) => {
+            // Pin the loaded maze's seed, so resetting generates it again.
+            if !layout.hand_drawn {
+                *maze_seed = MazeSeed {
+                    seed: layout.spec.seed,
+                    pinned: true,
+                };
+            }
+            let matching = settings.matching(&layout.spec);
+            settings.set_if_neq(matching);
+            loaded.current = Some((layout.clone(), matching));
+            layout
+        }
+        (None, Some(index)) => {
+            let (name, text) = LEVELS[index];
+            let level = Level::parse(text).expect("built-in levels should parse");
+            for warning in level.warnings {
+                warn!("Level {name:?}: {warning}");
+            }
+            level.layout
+        }
+        (None, None) if loaded.playing_level() => {
+            let (level, _) = loaded.current.as_ref().unwrap();
+            level.clone()
+        }
+        (None, None) => {
+            // `OsRng` is backed by `getrandom`, which also works in the
+            // browser.
+            if !maze_seed.pinned {
+                maze_seed.seed = OsRng.next_u64();
+            }
+            let difficulty = settings.difficulty;
+            let generate = |spec: &MazeSpec| {
+                let Some(difficulty) = difficulty else {
+                    return MazeLayout::generate(spec);
+                };
+                let layout = MazeLayout::generate_with_difficulty(spec, difficulty)?;
+                if let Some(metrics) = layout.metrics() {
+                    info!(
+                        "Aiming for {}, generated seed {:016x} with {}:\n{metrics}",
+                        difficulty.name().to_lowercase(),
+                        layout.spec.seed,
+                        layout.spec.algorithm,
+                    );
+                }
+                Ok(layout)
+            };
+            let spec = match &loaded.current {
+                Some((layout, _)) => MazeSpec {
+                    seed: maze_seed.seed,
+                    ..layout.spec.clone()
+                },
+                None => settings.spec(maze_seed.seed),
+            };
+            let layout = match generate(&spec) {
+                Ok(layout) => layout,
+                Err(err) => {
+                    // Small mazes can miss every cell of a mask, so go
+                    // without.
+                    warn!("Can't use the maze's mask: {err}");
+                    settings.mask = None;
+                    generate(&MazeSpec { mask: None, ..spec })
+                        .expect("unmasked mazes always have rooms")
+                }
+            };
+            // Aiming for a difficulty can change the seed and the algorithm,
+            // so show what the maze was really generated with.
+            maze_seed.set_if_neq(MazeSeed {
+                seed: layout.spec.seed,
+                ..*maze_seed
+            });
+            if settings.algorithm != layout.spec.algorithm {
+                settings.algorithm = layout.spec.algorithm;
+            }
+            layout
+        }
+    };
+    let sizes = &SIZES;
+    let mut maze = ChunkedMaze::new(layout, &mut materials, sizes, settings.walls);
+    let layout = &maze.layout;
+    let start = sizes.room_center(layout, layout.start);
+    let goal = sizes.room_center(layout, layout.goal);
+    let key = record_key(&maze, &settings, &loaded);
+    let ghost = key.and_then(|key| records::load_ghost(&key));
+    commands.insert_resource(CurrentRecordKey(key));
+
     commands
         .spawn((MazeRoot, SpatialBundle::default()))
         .with_children(|commands| {
-            // Walls
-            let spawns = generate_walls(
-                commands,
-                &mut meshes,
-                &mut materials,
-                -SIDE_HALFLENGTH..=SIDE_HALFLENGTH,
-                -SIDE_HALFLENGTH..=SIDE_HALFLENGTH,
-                0xaaaaaaaa,
-                &Sizes {
-                    room_side_length: ROOM_SIDE_LENGTH,
-                    wall_radius: 0.1,
-                    room_height: 0.9,
-                },
-            );
+            // Floors, walls and ramps around the start; `stream_chunks` takes
+            // it from there.
+            for chunk in maze.chunks_near(start, STREAM_DISTANCE, sizes) {
+                maze.build_chunk(commands, &mut meshes, sizes, chunk);
+            }
 
             for mut avatar_tranform in &mut avatars {
-                avatar_tranform.translation = Vec3::new(
-                    (spawns.start.west_edge as f32 + 0.5) * ROOM_SIDE_LENGTH,
-                    0.0,
-                    (spawns.start.south_edge as f32 + 0.5) * ROOM_SIDE_LENGTH,
-                );
+                avatar_tranform.translation = start;
                 avatar_tranform.rotation = Quat::from_rotation_y(TAU * 1. / 8.);
             }
 
+            // Ghost of the best run so far
+            if let Some(ghost) = ghost {
+                commands
+                    .spawn((
+                        GhostAvatar { ghost, tick: 0 },
+                        SpatialBundle::from_transform(
+                            Transform::from_translation(start)
+                                .with_rotation(Quat::from_rotation_y(TAU * 1. / 8.)),
+                        ),
+                    ))
+                    .with_children(|children| {
+                        children.spawn(avatar_marker(
+                            &mut meshes,
+                            materials.add(StandardMaterial {
+                                base_color: Color::rgba(0.6, 0.8, 1.0, 0.4),
+                                alpha_mode: AlphaMode::Blend,
+                                unlit: true,
+                                ..default()
+                            }),
+                        ));
+                    });
+            }
+
             // Goal
             commands
                 .spawn(SpatialBundle {
-                    transform: Transform::from_xyz(
-                        (spawns.goal.west_edge as f32 + 0.5) * ROOM_SIDE_LENGTH,
-                        0.0,
-                        (spawns.goal.south_edge as f32 + 0.5) * ROOM_SIDE_LENGTH,
-                    ),
+                    transform: Transform::from_translation(goal),
                     ..default()
                 })
                 .with_children(|children| {
@@ -217,9 +793,11 @@ fn reset_maze(
                         ..default()
                     });
                     children.spawn((
-                        Collider::cylinder(10.0, 0.4 * ROOM_SIDE_LENGTH),
+                        Collider::cylinder(sizes.room_height / 2.0, 0.4 * ROOM_SIDE_LENGTH),
                         Sensor,
-                        SpatialBundle::default(),
+                        SpatialBundle::from_transform(Transform::from_translation(
+                            Vec3::Y * sizes.room_height / 2.0,
+                        )),
                     ));
                     children.spawn(PointLightBundle {
                         point_light: PointLight {
@@ -232,6 +810,296 @@ fn reset_maze(
                     });
                 });
         });
+    commands.insert_resource(maze);
+}
+
+/// Spawns the parts of the maze that the avatar has come near, and despawns
+/// the parts it has left behind.
+fn stream_chunks(
+    mut commands: Commands,
+    maze: Option<ResMut<ChunkedMaze>>,
+    roots: Query<Entity, With<MazeRoot>>,
+    chunks: Query<(Entity, &Chunk)>,
+    avatars: Query<&Transform, With<Avatar>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let (Some(mut maze), Ok(root), Ok(avatar)) = (maze, roots.get_single(), avatars.get_single())
+    else {
+        return;
+    };
+    let keep = maze.chunks_near(avatar.translation, UNLOAD_DISTANCE, &SIZES);
+    let mut wanted = maze.chunks_near(avatar.translation, STREAM_DISTANCE, &SIZES);
+    for (entity, chunk) in &chunks {
+        if !keep.contains(chunk) {
+            commands.entity(entity).despawn_recursive();
+        }
+        wanted.remove(chunk);
+    }
+    if !wanted.is_empty() {
+        commands.entity(root).with_children(|commands| {
+            for chunk in wanted {
+                maze.build_chunk(commands, &mut meshes, &SIZES, chunk);
+            }
+        });
+    }
+}
+
+/// Keeps the map centered on the avatar, without showing more space beyond
+/// the edge of the maze than it has to.  Mazes small enough to fit on the
+/// map are zoomed to fill it.
+fn follow_avatar_on_map(
+    maze: Option<Res<ChunkedMaze>>,
+    avatars: Query<&Transform, With<Avatar>>,
+    mut cameras: Query<(&mut Transform, &mut Projection, &RestrictToView), Without<Avatar>>,
+) {
+    let (Some(maze), Ok(avatar)) = (maze, avatars.get_single()) else {
+        return;
+    };
+    let (min, max) = maze.bounds();
+    let half_span = Vec2::splat(MAP_HALFLENGTH as f32 * ROOM_SIDE_LENGTH).min((max - min) / 2.0);
+    let center = Vec2::new(avatar.translation.x, avatar.translation.z).clamp(
+        (min + half_span).min((min + max) / 2.0),
+        (max - half_span).max((min + max) / 2.0),
+    );
+    for (mut transform, mut projection, restriction) in &mut cameras {
+        if restriction.0 != ViewMode::Map {
+            continue;
+        }
+        transform.translation.x = center.x;
+        transform.translation.z = center.y;
+        let Projection::Orthographic(ortho) = &*projection else {
+            continue;
+        };
+        let fits = matches!(
+            ortho.scaling_mode,
+            ScalingMode::AutoMin { min_width, min_height }
+                if min_width == half_span.x * 2.0 && min_height == half_span.y * 2.0
+        );
+        if !fits {
+            if let Projection::Orthographic(ortho) = &mut *projection {
+                ortho.scaling_mode = ScalingMode::AutoMin {
+                    min_width: half_span.x * 2.0,
+                    min_height: half_span.y * 2.0,
+                };
+            }
+        }
+    }
+}
+
+/// The rooms that might be in sight from the avatar's room.
+#[derive(Default)]
+struct InSight {
+    from: Option<Room>,
+    rooms: HashSet<Room>,
+}
+
+/// Hides the walls and corner posts that can't be seen from the avatar's
+/// room.  The map shows everything.
+fn cull_out_of_sight(
+    view: Res<CurrentView>,
+    maze: Option<Res<ChunkedMaze>>,
+    avatars: Query<&Transform, With<Avatar>>,
+    mut in_sight: Local<InSight>,
+    mut cullables: Query<(&Cullable, &mut Visibility)>,
+) {
+    let (Some(maze), Ok(avatar)) = (maze, avatars.get_single()) else {
+        return;
+    };
+    // Outside the maze, keep seeing what the last room could see.
+    let from = maze.room_containing(avatar.translation, &SIZES);
+    if maze.is_changed() || (from.is_some() && from != in_sight.from) {
+        *in_sight = InSight {
+            from,
+            rooms: from.map_or_else(HashSet::new, |from| maze.in_sight(from, SIGHT_PASSAGES)),
+        };
+    }
+
+    let cull = view.0 == ViewMode::FirstPerson && in_sight.from.is_some();
+    for (cullable, mut visibility) in &mut cullables {
+        let hidden = cull && !cullable.0.iter().any(|room| in_sight.rooms.contains(room));
+        visibility.set_if_neq(if hidden {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        });
+    }
+}
+
+/// Loads mazes dropped onto the window, replacing the current maze.  `.txt`
+/// files are hand-drawn levels, and `.replay` files are runs to replay;
+/// anything else should be JSON or RON.
+fn load_dropped_mazes(
+    mut commands: Commands,
+    mut drops: EventReader<FileDragAndDrop>,
+    mut loaded: ResMut<LoadedLayout>,
+    mut reset_request: ResMut<MazeNeedsReset>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for drop in drops.iter() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = drop else {
+            continue;
+        };
+        if path_buf.extension() == Some(OsStr::new("replay")) {
+            match read_replay(path_buf) {
+                Ok(replay) => start_replay(&mut commands, &mut next_state, replay),
+                Err(err) => warn!("Couldn't load {}: {err}", path_buf.display()),
+            }
+            continue;
+        }
+        let text = match std::fs::read_to_string(path_buf) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("Couldn't read {}: {err}", path_buf.display());
+                continue;
+            }
+        };
+        let layout = if path_buf.extension() == Some(OsStr::new("txt")) {
+            Level::parse(&text)
+                .map(|level| {
+                    for warning in level.warnings {
+                        warn!("{}: {warning}", path_buf.display());
+                    }
+                    level.layout
+                })
+                .map_err(|err| err.to_string())
+        } else {
+            MazeLayout::parse(&text).map_err(|err| err.to_string())
+        };
+        match layout {
+            Ok(layout) => {
+                loaded.next = Some(layout);
+                *reset_request = MazeNeedsReset(true);
+            }
+            Err(err) => warn!("Couldn't load {}: {err}", path_buf.display()),
+        }
+    }
+}
+
+/// Saves the current maze as RON in the working directory, along with SVG
+/// and PNG pictures of it, and logs a drawing of it (if it's square) for good
+/// measure.  The browser has nowhere to save to, so there it only gets
+/// logged.
+fn save_maze(keyboard: Res<Input<KeyCode>>, maze: Option<Res<ChunkedMaze>>) {
+    const SAVE_MAZE: [KeyCode; 2] = [KeyCode::C, KeyCode::J];
+    let (true, Some(maze)) = (keyboard.any_just_pressed(SAVE_MAZE), maze) else {
+        return;
+    };
+    let layout = &maze.layout;
+    if let Some(drawing) = layout.to_ascii() {
+        info!("Maze {:016x}:\n{drawing}", layout.spec.seed);
+    }
+    let options = ExportOptions::default();
+    let files = [
+        ("ron", Some(layout.to_ron().into_bytes())),
+        ("svg", Some(layout.to_svg(&options).into_bytes())),
+        ("png", layout.to_png(&options)),
+    ];
+    for (extension, contents) in files {
+        let path = format!("maze-{:016x}.{extension}", layout.spec.seed);
+        let Some(contents) = contents else {
+            warn!("The maze is too big to save to {path}");
+            continue;
+        };
+        match std::fs::write(&path, contents) {
+            Ok(()) => info!("Saved the maze to {path}"),
+            Err(err) => warn!("Couldn't save the maze to {path}: {err}"),
+        }
+    }
+}
+
+fn show_maze_info(
+    maze_seed: Res<MazeSeed>,
+    settings: Res<MazeSettings>,
+    mut texts: Query<&mut Text, With<MazeInfoText>>,
+) {
+    for mut text in &mut texts {
+        text.sections[0].value = match settings.level {
+            Some(index) => format!(
+                "Level {} of {}: {}\n{} walls\n",
+                index + 1,
+                LEVELS.len(),
+                LEVELS[index].0,
+                settings.walls.name(),
+            ),
+            None => format!(
+                "{}Seed {:016x}{}\n{} grid, {} rooms across\n{} shape\n{}\nBraid {:.0}%\nFloors {}\n{} difficulty\n{} walls\n",
+                settings
+                    .campaign
+                    .map_or(String::new(), |stage| format!("Campaign maze {}\n", stage + 1)),
+                maze_seed.seed,
+                if maze_seed.pinned { " (pinned)" } else { "" },
+                settings.topology.name(),
+                settings.side_halflength * 2,
+                settings.mask_name(),
+                settings.algorithm,
+                settings.braid * 100.0,
+                settings.floors,
+                settings.difficulty_name(),
+                settings.walls.name(),
+            ),
+        };
+    }
+}
+
+/// The key to keep the current maze's best time under.  Hand-drawn levels,
+/// built in or loaded, don't get best times, since they all share a seed.
+/// `reset_maze` works it out once per maze, and keeps it in
+/// `CurrentRecordKey`.
+fn record_key(
+    maze: &ChunkedMaze,
+    settings: &MazeSettings,
+    loaded: &LoadedLayout,
+) -> Option<RecordKey> {
+    if settings.level.is_some() || loaded.playing_level() {
+        return None;
+    }
+    Some(RecordKey::of(&maze.layout))
+}
+
+fn show_run_clock(
+    state: Res<State<GameState>>,
+    clock: Res<RunClock>,
+    best_times: Res<BestTimes>,
+    key: Res<CurrentRecordKey>,
+    mut texts: Query<&mut Text, With<RunClockText>>,
+) {
+    let mut shown = format!("Time {}", format_time(clock.ticks));
+    if state.0 == GameState::Replaying {
+        shown = format!("Replay\n{shown}");
+    }
+    if let Some(key) = key.0 {
+        shown += &format!(
+            "\nBest {}",
+            best_times
+                .get(&key)
+                .map_or("-:--.-".to_owned(), format_time),
+        );
+    }
+    for mut text in &mut texts {
+        if text.sections[0].value != shown {
+            text.sections[0].value = shown.clone();
+        }
+    }
+}
+
+/// Shows how many entities there are, and how many meshes get drawn.  Bevy
+/// doesn't batch meshes on its own, so the latter is the number of draw calls
+/// per pass.
+fn show_render_stats(
+    entities: Query<()>,
+    meshes: Query<&ComputedVisibility, With<Handle<Mesh>>>,
+    mut texts: Query<&mut Text, With<MazeInfoText>>,
+) {
+    let drawn = meshes
+        .iter()
+        .filter(|visibility| visibility.is_visible())
+        .count();
+    let stats = format!("Entities {}\nMeshes drawn {}", entities.iter().len(), drawn);
+    for mut text in &mut texts {
+        if text.sections[RENDER_STATS_SECTION].value != stats {
+            text.sections[RENDER_STATS_SECTION].value = stats.clone();
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -239,11 +1107,14 @@ fn map_user_input(
     keyboard: Res<Input<KeyCode>>,
     mouse: Res<Input<MouseButton>>,
     mut reset_request: ResMut<MazeNeedsReset>,
+    mut maze_seed: ResMut<MazeSeed>,
+    mut settings: ResMut<MazeSettings>,
     mut motion: EventReader<MouseMotion>,
     mut avatars: Query<(&mut Avatar, Option<&mut AvatarPitch>)>,
     mut windows: Query<&mut Window>,
     mut view: ResMut<CurrentView>,
     mut grabbed: ResMut<MouseGrabbed>,
+    progress: Res<CampaignProgress>,
 ) {
     view.set_if_neq(CurrentView(if keyboard.pressed(KeyCode::Tab) {
         ViewMode::Map
@@ -268,6 +1139,17 @@ fn map_user_input(
     const TURN_LEFT: [KeyCode; 2] = [KeyCode::A, KeyCode::Left];
     const TURN_RIGHT: [KeyCode; 3] = [KeyCode::D, KeyCode::Right, KeyCode::E];
     const RESET_MAZE: [KeyCode; 2] = [KeyCode::R, KeyCode::P];
+    const PIN_SEED: [KeyCode; 2] = [KeyCode::L, KeyCode::N];
+    const NEXT_ALGORITHM: [KeyCode; 2] = [KeyCode::G, KeyCode::I];
+    const NEXT_SELECTION: [KeyCode; 2] = [KeyCode::T, KeyCode::Y];
+    const NEXT_BRAID: [KeyCode; 2] = [KeyCode::B, KeyCode::X];
+    const NEXT_TOPOLOGY: [KeyCode; 1] = [KeyCode::H];
+    const NEXT_FLOORS: [KeyCode; 2] = [KeyCode::F, KeyCode::U];
+    const NEXT_MASK: [KeyCode; 1] = [KeyCode::M];
+    const NEXT_WALL_BATCHING: [KeyCode; 2] = [KeyCode::V, KeyCode::K];
+    const NEXT_SIZE: [KeyCode; 2] = [KeyCode::Z, KeyCode::Semicolon];
+    const NEXT_LEVEL: [KeyCode; 2] = [KeyCode::Q, KeyCode::Apostrophe];
+    const NEXT_DIFFICULTY: [KeyCode; 2] = [KeyCode::Minus, KeyCode::LBracket];
     let walking = if keyboard.any_pressed(WALK_FORWARD) {
         1.0
     } else {
@@ -304,58 +1186,196 @@ fn map_user_input(
         }
     }
 
+    let chosen = *settings;
     if keyboard.any_just_pressed(RESET_MAZE) {
         *reset_request = MazeNeedsReset(true);
     }
+    if keyboard.any_just_pressed(PIN_SEED) {
+        maze_seed.pinned = !maze_seed.pinned;
+    }
+    if keyboard.any_just_pressed(NEXT_TOPOLOGY) {
+        settings.topology = settings.topology.next();
+        if !settings.algorithm.supports(&settings.spec(maze_seed.seed)) {
+            settings.algorithm = Algorithm::default();
+        }
+        *reset_request = MazeNeedsReset(true);
+    }
+    if keyboard.any_just_pressed(NEXT_ALGORITHM) {
+        settings.algorithm = settings.algorithm.next();
+        while !settings.algorithm.supports(&settings.spec(maze_seed.seed)) {
+            settings.algorithm = settings.algorithm.next();
+        }
+        *reset_request = MazeNeedsReset(true);
+    }
+    if keyboard.any_just_pressed(NEXT_SELECTION) {
+        if let Algorithm::GrowingTree(selection) = settings.algorithm {
+            settings.algorithm = Algorithm::GrowingTree(selection.next_preset());
+            *reset_request = MazeNeedsReset(true);
+        }
+    }
+    if keyboard.any_just_pressed(NEXT_BRAID) {
+        settings.braid = if settings.braid >= 1.0 {
+            0.0
+        } else {
+            settings.braid + 0.25
+        };
+        *reset_request = MazeNeedsReset(true);
+    }
+    if keyboard.any_just_pressed(NEXT_FLOORS) {
+        settings.floors = settings.floors % MAX_FLOORS + 1;
+        *reset_request = MazeNeedsReset(true);
+    }
+    if keyboard.any_just_pressed(NEXT_MASK) {
+        settings.mask = match settings.mask {
+            None => Some(0),
+            Some(index) if index + 1 < MASKS.len() => Some(index + 1),
+            Some(_) => None,
+        };
+        if !settings.algorithm.supports(&settings.spec(maze_seed.seed)) {
+            settings.algorithm = Algorithm::default();
+        }
+        *reset_request = MazeNeedsReset(true);
+    }
+    if keyboard.any_just_pressed(NEXT_WALL_BATCHING) {
+        settings.walls = settings.walls.next();
+        *reset_request = MazeNeedsReset(true);
+    }
+    if keyboard.any_just_pressed(NEXT_SIZE) {
+        let index = SIDE_HALFLENGTHS
+            .iter()
+            .position(|half| *half == settings.side_halflength)
+            .unwrap_or(0);
+        settings.side_halflength = SIDE_HALFLENGTHS[(index + 1) % SIDE_HALFLENGTHS.len()];
+        *reset_request = MazeNeedsReset(true);
+    }
+    if keyboard.any_just_pressed(NEXT_LEVEL) {
+        settings.level = next_level(settings.level);
+        *reset_request = MazeNeedsReset(true);
+    }
+    if keyboard.any_just_pressed(NEXT_DIFFICULTY) {
+        settings.difficulty = match settings.difficulty {
+            None => Some(Difficulty::Easy),
+            Some(Difficulty::Hard) => None,
+            Some(difficulty) => Some(difficulty.next()),
+        };
+        *reset_request = MazeNeedsReset(true);
+    }
+    // Campaign stages choose their own mazes, so choosing anything about
+    // the maze by hand leaves the campaign.  How the walls are batched
+    // doesn't change the maze.
+    let maze_changed = MazeSettings {
+        walls: chosen.walls,
+        ..*settings
+    } != chosen;
+    if maze_changed {
+        settings.campaign = None;
+    }
+    if keyboard.any_just_pressed(CAMPAIGN) {
+        *settings = match settings.campaign {
+            Some(_) => MazeSettings {
+                campaign: None,
+                ..*settings
+            },
+            None => settings.for_campaign(progress.solved),
+        };
+        *reset_request = MazeNeedsReset(true);
+    }
+}
+
+fn select_map_floor(
+    keyboard: Res<Input<KeyCode>>,
+    view: Res<CurrentView>,
+    settings: Res<MazeSettings>,
+    avatars: Query<&Transform, With<Avatar>>,
+    mut map_floor: ResMut<MapFloor>,
+) {
+    const FLOOR_UP: [KeyCode; 1] = [KeyCode::PageUp];
+    const FLOOR_DOWN: [KeyCode; 1] = [KeyCode::PageDown];
+    let mut floor = map_floor.0;
+    if view.is_changed() && view.0 == ViewMode::Map {
+        // Start out showing whichever floor the avatar is on.
+        for transform in &avatars {
+            floor = SIZES.floor_containing(transform.translation.y);
+        }
+    }
+    if keyboard.any_just_pressed(FLOOR_UP) {
+        floor += 1;
+    }
+    if keyboard.any_just_pressed(FLOOR_DOWN) {
+        floor -= 1;
+    }
+    map_floor.set_if_neq(MapFloor(floor.clamp(0, settings.floors - 1)));
+}
+
+fn show_map_floor(
+    view: Res<CurrentView>,
+    map_floor: Res<MapFloor>,
+    mut chunks: Query<(&Chunk, &mut Visibility)>,
+) {
+    for (chunk, mut visibility) in &mut chunks {
+        let hidden = view.0 == ViewMode::Map && chunk.floor > map_floor.0;
+        visibility.set_if_neq(if hidden {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        });
+    }
 }
 
 fn move_avatars(
     mut query: Query<(&mut Transform, &Avatar, Option<&AvatarPitch>)>,
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     mut rapier: ResMut<RapierContext>,
 ) {
-    let delta_time = time.delta_seconds();
-    let avatar_collider = Collider::cylinder(0.5, 0.4);
+    // `Time` keeps counting whole frames during `FixedUpdate`.  Stepping by
+    // the fixed period instead is what makes a replayed run come out the
+    // same.
+    let delta_time = fixed_time.period.as_secs_f32();
+    let (avatar_collider, collider_offset) = avatar_collider();
     for (mut transform, avatar, pitch) in &mut query {
         let (current_yaw, current_pitch, current_roll) = transform.rotation.to_euler(EulerRot::YXZ);
         assert_eq!(current_roll, 0.0);
 
         let unit_step = Quat::from_rotation_y(current_yaw) * Vec3::Z;
         let step = unit_step * avatar.walk_speed * avatar.walking * delta_time;
-        if step != Vec3::ZERO {
-            let mut move_shape = |step: Vec3| -> Vec3 {
-                rapier
-                    .move_shape(
-                        step,
-                        &avatar_collider,
-                        transform.translation,
-                        transform.rotation,
-                        1.0,
-                        &MoveShapeOptions {
-                            up: Vec3::Y,
-                            offset: CharacterLength::Relative(0.1),
-                            slide: false,
-                            autostep: None,
-                            max_slope_climb_angle: TAU / 8.0,
-                            min_slope_slide_angle: 0.0,
-                            apply_impulse_to_dynamic_bodies: false,
-                            snap_to_ground: None,
-                        },
-                        QueryFilter::default().exclude_sensors(),
-                        |_| {},
-                    )
-                    .effective_translation
-            };
-            // First, try to move along the full step.  If that would produce an
-            // effective-movement of ZERO, try again by only moving along the X
-            // axis, then again by only moving along the Z axis.
-            let candidate_steps = [step, step * Vec3::X, step * Vec3::Z];
-            for candidate_step in candidate_steps {
-                let effective = move_shape(candidate_step);
-                if effective.length() > 0.01 {
-                    transform.translation += effective;
-                    break;
-                }
+        let fall = Vec3::NEG_Y * FALL_SPEED * delta_time;
+        let mut move_shape = |step: Vec3| -> Vec3 {
+            rapier
+                .move_shape(
+                    step,
+                    &avatar_collider,
+                    transform.translation + collider_offset,
+                    transform.rotation,
+                    1.0,
+                    &MoveShapeOptions {
+                        up: Vec3::Y,
+                        offset: CharacterLength::Relative(0.1),
+                        // Sliding is what lets the avatar walk along the
+                        // floor (and up ramps) while gravity pulls it down.
+                        slide: true,
+                        autostep: None,
+                        max_slope_climb_angle: TAU / 8.0,
+                        min_slope_slide_angle: TAU / 8.0,
+                        apply_impulse_to_dynamic_bodies: false,
+                        snap_to_ground: None,
+                    },
+                    QueryFilter::default().exclude_sensors(),
+                    |_| {},
+                )
+                .effective_translation
+        };
+        // First, try to move along the full step.  If that would produce an
+        // effective-movement of ZERO, try again by only moving along the X
+        // axis, then again by only moving along the Z axis.  Failing all
+        // that, just fall.
+        let candidate_steps = [step, step * Vec3::X, step * Vec3::Z, Vec3::ZERO];
+        for candidate_step in candidate_steps {
+            let effective = move_shape(candidate_step + fall);
+            if (effective * Vec3::new(1.0, 0.0, 1.0)).length() > 0.01
+                || candidate_step == Vec3::ZERO
+            {
+                transform.translation += effective;
+                break;
             }
         }
 
@@ -370,25 +1390,458 @@ fn move_avatars(
     }
 }
 
+/// Records the player's input on every tick up to the goal, for replays.
+fn record_inputs(
+    clock: Res<RunClock>,
+    mut inputs: ResMut<InputRecorder>,
+    avatars: Query<&Avatar>,
+    pitches: Query<&AvatarPitch>,
+) {
+    if clock.finished() {
+        return;
+    }
+    let pitch = pitches.iter().next().map_or(0.0, |pitch| pitch.pitch);
+    for avatar in &avatars {
+        inputs.0.push(InputFrame {
+            walking: avatar.walking,
+            turning: avatar.turning,
+            pitch,
+        });
+    }
+}
+
+/// Plays a replay's inputs back, one tick at a time, until the avatar
+/// reaches the goal or the inputs run out.  Then it says how the replay
+/// compares with the run it recorded, and with the best time on the maze,
+/// and pauses.
+#[allow(clippy::too_many_arguments)]
+fn feed_replay(
+    mut commands: Commands,
+    player: Option<ResMut<ReplayPlayer>>,
+    clock: Res<RunClock>,
+    best_times: Res<BestTimes>,
+    key: Res<CurrentRecordKey>,
+    mut avatars: Query<&mut Avatar>,
+    mut pitches: Query<&mut AvatarPitch>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mut player) = player else {
+        return;
+    };
+    let frame = match player.frames.get(player.tick) {
+        Some(frame) if !clock.finished() => *frame,
+        _ => {
+            let recorded = recording::run_ticks(&player.frames) as u32;
+            let mut verdict = format!(
+                "The replay {} after {}.\nThe recorded run took {}.",
+                if clock.finished() {
+                    "reached the goal"
+                } else {
+                    "ran out without reaching the goal"
+                },
+                format_time(clock.ticks),
+                format_time(recorded),
+            );
+            if let Some(best) = key.0.and_then(|key| best_times.get(&key)) {
+                verdict += &if clock.finished() && clock.ticks == best {
+                    "\nThat's exactly the best time on this maze.".to_owned()
+                } else {
+                    format!("\nThe best time on this maze is {}.", format_time(best))
+                };
+            }
+            info!("{verdict}");
+            commands.insert_resource(ReplayVerdict(Some(verdict)));
+            commands.remove_resource::<ReplayPlayer>();
+            next_state.set(GameState::Paused);
+            InputFrame {
+                walking: 0.0,
+                turning: 0.0,
+                pitch: 0.0,
+            }
+        }
+    };
+    player.tick += 1;
+    for mut avatar in &mut avatars {
+        avatar.walking = frame.walking;
+        avatar.turning = frame.turning;
+    }
+    for mut pitch in &mut pitches {
+        pitch.pitch = frame.pitch;
+    }
+}
+
+/// Records where the avatar is on every tick of a run, from when the clock
+/// starts.
+fn record_ghost(
+    clock: Res<RunClock>,
+    mut recorder: ResMut<GhostRecorder>,
+    avatars: Query<&Transform, With<Avatar>>,
+) {
+    if !clock.running {
+        return;
+    }
+    for transform in &avatars {
+        let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        recorder.0.frames.push(GhostFrame {
+            position: transform.translation.to_array(),
+            yaw,
+        });
+    }
+}
+
+/// Moves the ghost along its path, keeping pace with the run clock.  Once its
+/// path runs out, it waits at the goal.
+fn replay_ghost(clock: Res<RunClock>, mut ghosts: Query<(&mut GhostAvatar, &mut Transform)>) {
+    if !clock.running {
+        return;
+    }
+    for (mut ghost, mut transform) in &mut ghosts {
+        let Some(frame) = ghost.ghost.frames.get(ghost.tick).copied() else {
+            continue;
+        };
+        ghost.tick += 1;
+        transform.translation = Vec3::from_array(frame.position);
+        transform.rotation = Quat::from_rotation_y(frame.yaw);
+    }
+}
+
+/// The triangle that marks where the avatar, or its ghost, is and which way
+/// it's facing.
+fn avatar_marker(meshes: &mut Assets<Mesh>, material: Handle<StandardMaterial>) -> PbrBundle {
+    PbrBundle {
+        mesh: meshes.add(shape::RegularPolygon::new(0.5, 3).into()),
+        material,
+        transform: Transform::from_rotation(
+            Quat::from_rotation_y(TAU / 6.0) * Quat::from_rotation_x(-TAU / 4.0),
+        )
+        .with_translation(Vec3::Y * 0.1),
+        ..default()
+    }
+}
+
+/// The avatar's collider, and where it sits relative to the avatar.  It stands
+/// a little above the avatar's feet, so it doesn't start out touching the
+/// floor.
+fn avatar_collider() -> (Collider, Vec3) {
+    (
+        Collider::cylinder(AVATAR_HALF_HEIGHT, AVATAR_RADIUS),
+        Vec3::Y * (AVATAR_HALF_HEIGHT + 0.05),
+    )
+}
+
+/// The level after `level` in `LEVELS`, going back to generated mazes after
+/// the last one.
+fn next_level(level: Option<usize>) -> Option<usize> {
+    match level {
+        None => Some(0),
+        Some(index) if index + 1 < LEVELS.len() => Some(index + 1),
+        Some(_) => None,
+    }
+}
+
+/// Whether an avatar standing at `transform` has reached the goal.
+fn touching_goal(rapier: &RapierContext, transform: &Transform) -> bool {
+    let (avatar_collider, collider_offset) = avatar_collider();
+    rapier
+        .intersection_with_shape(
+            transform.translation + collider_offset,
+            transform.rotation,
+            &avatar_collider,
+            QueryFilter::default(),
+        )
+        .is_some()
+}
+
 fn check_goal(
-    query: Query<&Transform, With<Avatar>>,
+    clock: Res<RunClock>,
+    reset_request: Res<MazeNeedsReset>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if clock.finished() && !reset_request.0 {
+        next_state.set(GameState::LevelComplete);
+    }
+}
+
+/// Keeps the time of the run that just reached the goal if it's the best on
+/// this maze, along with a ghost and a replay of the run.
+fn keep_best_run(
+    maze: Option<Res<ChunkedMaze>>,
+    key: Res<CurrentRecordKey>,
+    clock: Res<RunClock>,
+    mut best_times: ResMut<BestTimes>,
+    recorder: Res<GhostRecorder>,
+    inputs: Res<InputRecorder>,
+) {
+    let Some(maze) = maze else {
+        return;
+    };
+    let Some(key) = key.0 else {
+        return;
+    };
+    if best_times.record(key, clock.ticks) {
+        best_times.save();
+        records::save_ghost(&key, &recorder.0);
+        records::save_replay(
+            &key,
+            &InputReplay {
+                layout: maze.layout.clone(),
+                frames: inputs.0.clone(),
+            },
+        );
+    }
+}
+
+/// Starts the clock on the first tick the avatar moves, and counts every
+/// tick from then until the one it reaches the goal on.  That's the same
+/// ticks `recording::run_ticks` counts in a recording of the run.
+fn tick_run_clock(
+    mut clock: ResMut<RunClock>,
     rapier: Res<RapierContext>,
+    avatars: Query<(&Transform, &Avatar)>,
+) {
+    for (transform, avatar) in &avatars {
+        let moving = avatar.walking != 0.0 || avatar.turning != 0.0;
+        if clock.ticks == 0 && moving {
+            clock.running = true;
+        }
+        if clock.running {
+            clock.ticks += 1;
+            clock.running = !touching_goal(&rapier, transform);
+        }
+    }
+}
+
+const CONFIRM: [KeyCode; 2] = [KeyCode::Return, KeyCode::Space];
+const PAUSE: [KeyCode; 1] = [KeyCode::Escape];
+const CAMPAIGN: [KeyCode; 1] = [KeyCode::Key1];
+
+fn start_game(
+    keyboard: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
     mut reset_request: ResMut<MazeNeedsReset>,
+    mut settings: ResMut<MazeSettings>,
+    progress: Res<CampaignProgress>,
 ) {
-    let avatar_collider = Collider::cylinder(0.5, 0.4); // TODO -- duplicated from `move_avatars`
-    for xform in &query {
-        if rapier
-            .intersection_with_shape(
-                xform.translation,
-                xform.rotation,
-                &avatar_collider,
-                QueryFilter::default(),
-            )
-            .is_some()
-        {
-            *reset_request = MazeNeedsReset(true);
+    if keyboard.any_just_pressed(CONFIRM) {
+        next_state.set(GameState::Playing);
+    }
+    if keyboard.any_just_pressed(CAMPAIGN) {
+        *settings = settings.for_campaign(progress.solved);
+        *reset_request = MazeNeedsReset(true);
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn pause_game(keyboard: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if keyboard.any_just_pressed(PAUSE) {
+        next_state.set(GameState::Paused);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn use_pause_menu(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut reset_request: ResMut<MazeNeedsReset>,
+    maze: Option<Res<ChunkedMaze>>,
+    inputs: Res<InputRecorder>,
+    player: Option<Res<ReplayPlayer>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    const NEW_MAZE: [KeyCode; 2] = [KeyCode::R, KeyCode::P];
+    const WATCH_REPLAY: [KeyCode; 2] = [KeyCode::W, KeyCode::Comma];
+    const SAVE_REPLAY: [KeyCode; 2] = [KeyCode::S, KeyCode::O];
+    const QUIT: [KeyCode; 2] = [KeyCode::Q, KeyCode::Apostrophe];
+    let replay = || {
+        maze.as_ref().map(|maze| InputReplay {
+            layout: maze.layout.clone(),
+            frames: inputs.0.clone(),
+        })
+    };
+    // A replay that's still going was paused part way, so pick it up again.
+    if keyboard.any_just_pressed(PAUSE) || keyboard.any_just_pressed(CONFIRM) {
+        next_state.set(match player {
+            Some(_) => GameState::Replaying,
+            None => GameState::Playing,
+        });
+    }
+    // A new maze is the player's to play, so it ends any replay.
+    if keyboard.any_just_pressed(NEW_MAZE) {
+        commands.remove_resource::<ReplayPlayer>();
+        *reset_request = MazeNeedsReset(true);
+        next_state.set(GameState::Playing);
+    }
+    if keyboard.any_just_pressed(SAVE_REPLAY) && !cfg!(target_family = "wasm") {
+        if let Some(replay) = replay() {
+            let path = format!("maze-{:016x}.replay", replay.layout.spec.seed);
+            match std::fs::write(&path, replay.to_bytes()) {
+                Ok(()) => info!("Saved a replay of the run to {path}"),
+                Err(err) => warn!("Couldn't save a replay of the run to {path}: {err}"),
+            }
         }
     }
+    if keyboard.any_just_pressed(WATCH_REPLAY) {
+        if let Some(replay) = replay() {
+            start_replay(&mut commands, &mut next_state, replay);
+        }
+    }
+    // The browser tab is the browser's to close.
+    if keyboard.any_just_pressed(QUIT) && !cfg!(target_family = "wasm") {
+        exit.send(AppExit);
+    }
+}
+
+/// Moves on from a solved maze: to the next level when playing levels, and
+/// to a new maze otherwise.
+fn finish_level(
+    keyboard: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut reset_request: ResMut<MazeNeedsReset>,
+    mut settings: ResMut<MazeSettings>,
+) {
+    if keyboard.any_just_pressed(CONFIRM) {
+        if settings.level.is_some() {
+            settings.level = next_level(settings.level);
+        }
+        if let Some(stage) = settings.campaign {
+            *settings = settings.for_campaign(stage + 1);
+        }
+        *reset_request = MazeNeedsReset(true);
+        next_state.set(GameState::Playing);
+    }
+}
+
+/// Counts a solved campaign maze towards the player's progress.
+fn advance_campaign(settings: Res<MazeSettings>, mut progress: ResMut<CampaignProgress>) {
+    if let Some(stage) = settings.campaign {
+        if stage >= progress.solved {
+            progress.solved = stage + 1;
+            progress.save();
+        }
+    }
+}
+
+fn forget_replay_verdict(mut verdict: ResMut<ReplayVerdict>) {
+    verdict.0 = None;
+}
+
+/// Lets go of the mouse whenever play stops, so the menus can be used.
+fn release_mouse(mut windows: Query<&mut Window>, mut grabbed: ResMut<MouseGrabbed>) {
+    for mut window in &mut windows {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
+    grabbed.set_if_neq(MouseGrabbed(false));
+}
+
+/// Shows the screen for the state the game just entered.
+#[allow(clippy::too_many_arguments)]
+fn show_screen(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    font: Res<UiFont>,
+    maze: Option<Res<ChunkedMaze>>,
+    clock: Res<RunClock>,
+    best_times: Res<BestTimes>,
+    key: Res<CurrentRecordKey>,
+    settings: Res<MazeSettings>,
+    verdict: Res<ReplayVerdict>,
+) {
+    let (title, body) = match state.0 {
+        GameState::Title => (
+            "Browser Maze".to_owned(),
+            "Find your way to the golden ring.\n\nPress Enter to start\nPress 1 to play the campaign"
+                .to_owned(),
+        ),
+        GameState::Paused => (
+            "Paused".to_owned(),
+            format!(
+                "{}Esc - Resume\nR - Start over with a new maze\nW - Watch this run again{}",
+                verdict
+                    .0
+                    .as_ref()
+                    .map_or(String::new(), |verdict| format!("{verdict}\n\n")),
+                // There's nowhere to save files in the browser, and the
+                // browser tab is the browser's to close.
+                if cfg!(target_family = "wasm") {
+                    ""
+                } else {
+                    "\nS - Save a replay of this run\nQ - Quit"
+                },
+            ),
+        ),
+        GameState::LevelComplete => {
+            let mut body = format!("Time {}\n", format_time(clock.ticks));
+            if let Some(best) = key.0.and_then(|key| best_times.get(&key)) {
+                body += &if best == clock.ticks {
+                    "New best time!\n".to_owned()
+                } else {
+                    format!("Best {}\n", format_time(best))
+                };
+            }
+            body += "\n";
+            if let Some(metrics) = maze.and_then(|maze| maze.layout.metrics()) {
+                body += &format!(
+                    "Shortest way {} steps\nDecision points {}\nDead ends {}\nDifficulty score {:.2}\n",
+                    metrics.solution_length,
+                    metrics.decision_points,
+                    metrics.dead_ends,
+                    metrics.difficulty(),
+                );
+            }
+            body += match (settings.level, settings.campaign) {
+                (Some(index), _) if index + 1 < LEVELS.len() => "\nPress Enter for the next level",
+                (Some(_), _) => "\nPress Enter to go back to random mazes",
+                (None, Some(_)) => "\nPress Enter for the next, bigger maze",
+                (None, None) => "\nPress Enter for the next maze",
+            };
+            ("Maze solved!".to_owned(), body)
+        }
+        GameState::Playing | GameState::Replaying => return,
+    };
+
+    let style = |font_size: f32| TextStyle {
+        font: font.0.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            Screen,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+        ))
+        .with_children(|screen| {
+            screen.spawn(
+                TextBundle::from_sections([
+                    TextSection::new(title + "\n\n", style(48.0)),
+                    TextSection::new(body, style(24.0)),
+                ])
+                .with_text_alignment(TextAlignment::Center),
+            );
+        });
+}
+
+fn hide_screen(mut commands: Commands, screens: Query<Entity, With<Screen>>) {
+    for screen in &screens {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+
+/// Formats a number of ticks as minutes, seconds and tenths, like `1:02.3`.
+fn format_time(ticks: u32) -> String {
+    let tenths = ticks * 10 / TICKS_PER_SECOND;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
 }
 
 fn switch_camera(current: Res<CurrentView>, mut cameras: Query<(&mut Camera, &RestrictToView)>) {
@@ -1,319 +1,960 @@
-use std::{f32::consts::TAU, ops::RangeInclusive};
+//! Maze generation, independent of the ECS.
+//!
+//! Everything in this module is plain data: a [`MazeLayout`] can be generated,
+//! inspected, and thrown away without ever touching Bevy.  Turning a layout
+//! into meshes and colliders is the job of the game binary's `spawn` module.
 
-use bevy::{
-    prelude::*,
-    utils::{HashMap, HashSet},
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::{Range, RangeInclusive},
 };
-use bevy_rapier3d::prelude::*;
-use petgraph::{algo::floyd_warshall, graph::NodeIndex, Graph, Undirected};
-use rand::{rngs::SmallRng, seq::IteratorRandom, SeedableRng};
 
-#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
-pub struct Room {
-    // The lower bound of this room's `x` coordinates.
-    pub west_edge: i32,
-    // The lower bound of this room`s `z` coordinates.
-    pub south_edge: i32,
-}
+use petgraph::{
+    graph::{EdgeIndex, NodeIndex},
+    unionfind::UnionFind,
+    visit::EdgeRef,
+    Graph, Undirected,
+};
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug)]
-struct Wall {
-    sw_corner: (i32, i32),
-    orientation: WallOrientation,
-    disposition: Disposition,
+pub mod algorithms;
+pub mod export;
+pub mod format;
+pub mod level;
+pub mod mask;
+pub mod metrics;
+pub mod solver;
+pub mod topology;
+pub mod visibility;
+
+pub use algorithms::{Algorithm, MazeAlgorithm, Selection};
+pub use export::{ExportOptions, MAX_PNG_PIXELS};
+pub use format::{InvalidLayout, LoadError};
+pub use level::{Level, LevelError, LevelWarning};
+pub use mask::{Mask, MaskError};
+pub use metrics::{Difficulty, Metrics};
+pub use topology::{Side, Topology};
+
+/// A room's position in the grid.  Where that puts it in space depends on the
+/// maze's [`Topology`].
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Room {
+    // Counts rooms along `x`.
+    pub column: i32,
+    // Counts rooms along `z`.
+    pub row: i32,
+    // Counts floors upward from the ground floor, which is 0.
+    pub floor: i32,
 }
 
-#[derive(Copy, Clone, Debug)]
-enum WallOrientation {
-    ParallelToX,
-    ParallelToZ,
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wall {
+    /// The room this wall belongs to.  Interior walls belong to whichever of
+    /// their two rooms has them as one of `Topology::owned_sides`; border
+    /// walls belong to the room inside the maze.  The exception is where a
+    /// polar room has some of its outward neighbors but not all: the rest of
+    /// its outward side is walled off by the missing rooms' inward walls.
+    pub room: Room,
+    pub side: Side,
+    pub disposition: Disposition,
 }
 
-#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
-enum Disposition {
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Disposition {
     Present,
     Absent,
     Unknown,
 }
 
-#[derive(Debug)]
-pub struct Sizes {
-    pub room_side_length: f32,
-    pub wall_radius: f32,
-    pub room_height: f32,
+/// Everything that goes into generating a maze.  Generating the same spec
+/// twice produces the same maze, on any platform and in any release: mazes
+/// depend on nothing but the spec, and `tests/golden.rs` keeps it that way.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MazeSpec {
+    pub topology: Topology,
+    /// The room columns, spanned like lattice coordinates: rooms fill
+    /// `x_min..x_max`.  Polar mazes ignore this, since each ring has its own
+    /// number of columns.
+    pub x_range: RangeInclusive<i32>,
+    /// The room rows, spanned like lattice coordinates: rooms fill
+    /// `z_min..z_max`.  Polar mazes always start at the center, so only the
+    /// number of rows (rings) matters.
+    pub z_range: RangeInclusive<i32>,
+    /// Which rooms of the grid to keep, if not all of them.  Only the
+    /// biggest connected region the mask leaves gets used.
+    pub mask: Option<Mask>,
+    /// How many floors to stack (at least one).  Each floor is a maze of its
+    /// own, joined to the next floor up by a single ramp.
+    pub floors: i32,
+    pub algorithm: Algorithm,
+    /// The fraction (from 0.0 to 1.0) of dead ends to remove by knocking down
+    /// extra walls.  Anything above zero gives the maze loops.
+    pub braid: f32,
+    pub seed: u64,
 }
 
-#[derive(Debug)]
-pub struct SpawnPositions {
+/// A fully generated maze.
+#[derive(Clone, Debug)]
+pub struct MazeLayout {
+    /// What this maze was generated from.
+    pub spec: MazeSpec,
+    /// Every room, connected by every interior wall.  Passages between rooms
+    /// are walls with `Disposition::Absent`.
+    pub graph: Graph<Room, Wall, Undirected>,
+    /// The walls around the outside of the maze, which are always present.
+    pub border: Vec<Wall>,
+    /// Where the player starts.
     pub start: Room,
+    /// Where the player is trying to get to.
     pub goal: Room,
+    /// Whether the maze was drawn by hand, as a [`Level`], rather than
+    /// generated.  Its spec then only describes the grid it's drawn on, and
+    /// generating that spec gives an unrelated maze.
+    pub hand_drawn: bool,
 }
 
-pub fn generate_walls(
-    commands: &mut ChildBuilder,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    x_range: RangeInclusive<i32>,
-    z_range: RangeInclusive<i32>,
-    seed: u64,
-    sizes: &Sizes,
-) -> SpawnPositions {
-    let border_walls = iter_border_walls(x_range.clone(), z_range.clone());
-    let graph = choose_walls(x_range.clone(), z_range.clone(), seed);
-    let inner_walls = graph.edge_weights().copied();
-    build_walls(
-        commands,
-        meshes,
-        materials,
-        x_range,
-        z_range,
-        border_walls.chain(inner_walls),
-        sizes,
-    );
-    choose_spawn_positions(&graph)
+/// A ramp between two floors.  It climbs from the lower room's passage to the
+/// upper room's, which leaves no room for a floor under the upper room.
+#[derive(Copy, Clone, Debug)]
+pub struct Ramp {
+    pub lower: Room,
+    pub upper: Room,
+    /// Where the ramp starts, on the lower floor.
+    pub bottom: (f32, f32),
+    /// Where the ramp ends, on the upper floor.
+    pub top: (f32, f32),
 }
 
-fn iter_border_walls(
-    x_range: RangeInclusive<i32>,
-    z_range: RangeInclusive<i32>,
-) -> impl Iterator<Item = Wall> {
-    let (x_min, x_max) = (*x_range.start(), *x_range.end());
-    let (z_min, z_max) = (*z_range.start(), *z_range.end());
-
-    let ns_walls = (x_min..x_max).flat_map(move |x| {
-        [z_min, z_max].into_iter().map(move |z| Wall {
-            sw_corner: (x, z),
-            orientation: WallOrientation::ParallelToX,
-            disposition: Disposition::Present,
-        })
-    });
-    let ew_walls = (z_min..z_max).flat_map(move |z| {
-        [x_min, x_max].into_iter().map(move |x| Wall {
-            sw_corner: (x, z),
-            orientation: WallOrientation::ParallelToZ,
-            disposition: Disposition::Present,
+/// The random number generator that carves every maze.  Unlike `SmallRng`,
+/// which is a different generator on 32-bit targets like the browser, it
+/// gives the same numbers for the same seed everywhere, and its output won't
+/// change under us in a new version of `rand`.
+pub type MazeRng = Xoshiro256PlusPlus;
+
+#[derive(Debug)]
+pub struct SpawnPositions {
+    pub start: Room,
+    pub goal: Room,
+}
+
+impl MazeLayout {
+    /// Generates `spec`'s maze.  Fails if the spec's mask leaves no rooms to
+    /// put a maze in.
+    pub fn generate(spec: &MazeSpec) -> Result<Self, MaskError> {
+        let grid = choose_walls(spec);
+        if grid.room_count() == 0 {
+            return Err(MaskError::NoRooms);
+        }
+        let SpawnPositions { start, goal } = match spec.topology {
+            Topology::Polar => choose_polar_spawn_positions(&grid),
+            _ => choose_spawn_positions(&grid.graph),
+        };
+        Ok(MazeLayout {
+            spec: spec.clone(),
+            graph: grid.graph,
+            border: grid.border,
+            start,
+            goal,
+            hand_drawn: false,
         })
-    });
+    }
+
+    /// Every wall of the maze: the outer border first, then the interior.
+    pub fn walls(&self) -> impl Iterator<Item = Wall> + '_ {
+        self.border.iter().chain(self.graph.edge_weights()).copied()
+    }
+
+    /// The floors of the maze, from the ground up.
+    pub fn floors(&self) -> Range<i32> {
+        0..self.spec.floors.max(1)
+    }
+
+    /// Every room corner on `floor`, without duplicates.  This is where the
+    /// corner posts go.
+    pub fn corners(&self, floor: i32) -> Vec<(f32, f32)> {
+        self.corners_with_rooms(floor)
+            .into_iter()
+            .map(|(corner, _)| corner)
+            .collect()
+    }
+
+    /// Like `corners`, but also lists the rooms that meet at each corner.
+    pub fn corners_with_rooms(&self, floor: i32) -> Vec<((f32, f32), Vec<Room>)> {
+        // Neighboring rooms compute shared corners separately, so they only
+        // agree to within rounding error.
+        let key = |(x, z): (f32, f32)| ((x * 1024.0).round() as i64, (z * 1024.0).round() as i64);
+        let mut indices = HashMap::new();
+        let mut corners = Vec::<((f32, f32), Vec<Room>)>::new();
+        for room in self.graph.node_weights().filter(|room| room.floor == floor) {
+            for corner in self.spec.topology.room_corners(*room) {
+                let index = *indices.entry(key(corner)).or_insert_with(|| {
+                    corners.push((corner, Vec::new()));
+                    corners.len() - 1
+                });
+                corners[index].1.push(*room);
+            }
+        }
+        corners
+    }
 
-    ns_walls.chain(ew_walls)
+    /// Every ramp between floors.
+    pub fn ramps(&self) -> Vec<Ramp> {
+        let topology = self.spec.topology;
+        // A ramp ends in the middle of its room's passage.  Rooms should only
+        // ever have the one, but don't count on it.
+        let end = |room: NodeIndex| match only_passage(&self.graph, room) {
+            Some(wall) => topology.wall_midpoint(&self.graph[wall]),
+            None => topology.room_center(self.graph[room]),
+        };
+        self.graph
+            .edge_references()
+            .filter(|edge| {
+                edge.weight().side.is_vertical() && edge.weight().disposition == Disposition::Absent
+            })
+            .map(|edge| {
+                let (upper, lower) = if self.graph[edge.source()] == edge.weight().room {
+                    (edge.source(), edge.target())
+                } else {
+                    (edge.target(), edge.source())
+                };
+                Ramp {
+                    lower: self.graph[lower],
+                    upper: self.graph[upper],
+                    bottom: end(lower),
+                    top: end(upper),
+                }
+            })
+            .collect()
+    }
 }
 
-fn choose_walls(
-    x_range: RangeInclusive<i32>,
-    z_range: RangeInclusive<i32>,
-    seed: u64,
-) -> Graph<Room, Wall, Undirected> {
-    let (x_min, x_max) = (*x_range.start(), *x_range.end());
-    let (z_min, z_max) = (*z_range.start(), *z_range.end());
-
-    let mut graph = Graph::<Room, Wall, Undirected>::new_undirected();
-    let mut ids_by_room = HashMap::<Room, NodeIndex>::new();
-
-    // Define rooms
-    for x in x_min..x_max {
-        for z in z_min..z_max {
-            let room = Room {
-                west_edge: x,
-                south_edge: z,
-            };
+/// Every room of a maze, connected by every wall between neighboring rooms.
+/// Fresh grids have a single floor; stacking several also connects each room
+/// to the room above it.  A fresh grid has every wall `Unknown`, ready for a
+/// [`MazeAlgorithm`] to carve passages into.
+#[derive(Clone, Debug)]
+pub struct Grid {
+    pub topology: Topology,
+    pub x_range: RangeInclusive<i32>,
+    pub z_range: RangeInclusive<i32>,
+    pub graph: Graph<Room, Wall, Undirected>,
+    /// The walls that don't lead to another room.
+    pub border: Vec<Wall>,
+    ids_by_room: HashMap<Room, NodeIndex>,
+}
+
+impl Grid {
+    /// Builds a single floor with every room in the ranges, or just the ones
+    /// `mask` keeps.  Rooms a mask cuts off from the rest are dropped too,
+    /// since no maze could join them up.
+    pub fn new(
+        topology: Topology,
+        x_range: RangeInclusive<i32>,
+        z_range: RangeInclusive<i32>,
+        mask: Option<&Mask>,
+    ) -> Self {
+        let (x_min, x_max) = (*x_range.start(), *x_range.end());
+        let (z_min, z_max) = (*z_range.start(), *z_range.end());
+
+        // Define rooms
+        let mut rooms = Vec::new();
+        let mut add_room = |room: Room| rooms.push(room);
+        match topology {
+            Topology::Polar => {
+                for row in 0..z_max - z_min {
+                    for column in 0..topology::polar_ring_size(row) {
+                        add_room(Room {
+                            column,
+                            row,
+                            floor: 0,
+                        });
+                    }
+                }
+            }
+            _ => {
+                for column in x_min..x_max {
+                    for row in z_min..z_max {
+                        add_room(Room {
+                            column,
+                            row,
+                            floor: 0,
+                        });
+                    }
+                }
+            }
+        }
+
+        let Some(mask) = mask else {
+            return Self::with_rooms(topology, x_range, z_range, rooms);
+        };
+        let grid = Self::with_rooms(topology, x_range, z_range, mask.apply(topology, rooms));
+        let region = grid.largest_region();
+        if region.len() == grid.room_count() {
+            return grid;
+        }
+        Self::with_rooms(grid.topology, grid.x_range, grid.z_range, region)
+    }
+
+    /// Builds a single floor out of `rooms`, with `Unknown` walls between
+    /// neighbors.
+    fn with_rooms(
+        topology: Topology,
+        x_range: RangeInclusive<i32>,
+        z_range: RangeInclusive<i32>,
+        rooms: Vec<Room>,
+    ) -> Self {
+        let mut graph = Graph::<Room, Wall, Undirected>::new_undirected();
+        let mut ids_by_room = HashMap::<Room, NodeIndex>::new();
+        for room in rooms {
             let room_id = graph.add_node(room);
             ids_by_room.insert(room, room_id);
         }
+
+        // Define (potential) walls.  Sides that don't lead to another room
+        // are part of the border.
+        let mut border = Vec::new();
+        for r0 in graph.node_indices() {
+            let room = graph[r0];
+            for &side in topology.sides(room) {
+                let neighbors = topology.neighbors(room, side);
+                let missing = neighbors
+                    .iter()
+                    .filter(|neighbor| !ids_by_room.contains_key(neighbor))
+                    .collect::<Vec<_>>();
+                if missing.len() == neighbors.len() {
+                    border.push(Wall {
+                        room,
+                        side,
+                        disposition: Disposition::Present,
+                    });
+                    continue;
+                }
+                // Only part of a split polar side leads out of the maze.
+                border.extend(missing.into_iter().map(|neighbor| Wall {
+                    room: *neighbor,
+                    side: side.opposite(),
+                    disposition: Disposition::Present,
+                }));
+                if !topology.owned_sides().contains(&side) {
+                    continue;
+                }
+                for neighbor in &neighbors {
+                    if let Some(&r1) = ids_by_room.get(neighbor) {
+                        let wall = Wall {
+                            room,
+                            side,
+                            disposition: Disposition::Unknown,
+                        };
+                        graph.add_edge(r0, r1, wall);
+                    }
+                }
+            }
+        }
+
+        Grid {
+            topology,
+            x_range,
+            z_range,
+            graph,
+            border,
+            ids_by_room,
+        }
     }
 
-    // Define (potential) walls
-    for x in x_min..x_max {
-        for z in z_min..z_max {
-            let r0 = *ids_by_room
-                .get(&Room {
-                    west_edge: x,
-                    south_edge: z,
-                })
-                .unwrap();
-            if z > z_min {
-                let wall = Wall {
-                    sw_corner: (x, z),
-                    orientation: WallOrientation::ParallelToX,
-                    disposition: Disposition::Unknown,
-                };
-                let r1 = *ids_by_room
-                    .get(&Room {
-                        west_edge: x,
-                        south_edge: z - 1,
-                    })
-                    .unwrap();
-                graph.add_edge(r0, r1, wall);
+    /// The rooms of the biggest group that could be joined up by knocking
+    /// down walls, in order.  Ties go to whichever group comes first.
+    fn largest_region(&self) -> Vec<Room> {
+        let mut regions = UnionFind::<usize>::new(self.room_count());
+        for edge in self.graph.edge_references() {
+            regions.union(edge.source().index(), edge.target().index());
+        }
+        let labels = regions.into_labeling();
+        let mut sizes = HashMap::<usize, usize>::new();
+        for label in &labels {
+            *sizes.entry(*label).or_default() += 1;
+        }
+        let Some(&largest) = labels.iter().rev().max_by_key(|label| sizes[label]) else {
+            return vec![];
+        };
+        self.graph
+            .node_indices()
+            .filter(|room| labels[room.index()] == largest)
+            .map(|room| self.graph[room])
+            .collect()
+    }
+
+    /// Stacks single-floor grids into one, bottom floor first.  Each room
+    /// gets an `Unknown` wall (its floor) to the room below it.
+    fn stack(floors: Vec<Grid>) -> Self {
+        let mut floors = floors.into_iter();
+        let mut grid = floors.next().expect("a maze needs at least one floor");
+        for (floor, upper) in (1..).zip(floors) {
+            let on_floor = |wall: &Wall| Wall {
+                room: Room { floor, ..wall.room },
+                ..*wall
+            };
+            let first_id = grid.graph.node_count();
+            for room in upper.graph.node_weights() {
+                let room = Room { floor, ..*room };
+                let room_id = grid.graph.add_node(room);
+                grid.ids_by_room.insert(room, room_id);
+                let below = grid.topology.neighbor(room, Side::Down);
+                if let Some(&below_id) = grid.ids_by_room.get(&below) {
+                    let wall = Wall {
+                        room,
+                        side: Side::Down,
+                        disposition: Disposition::Unknown,
+                    };
+                    grid.graph.add_edge(room_id, below_id, wall);
+                }
             }
-            if x > x_min {
-                let wall = Wall {
-                    sw_corner: (x, z),
-                    orientation: WallOrientation::ParallelToZ,
-                    disposition: Disposition::Unknown,
-                };
-                let r1 = *ids_by_room
-                    .get(&Room {
-                        west_edge: x - 1,
-                        south_edge: z,
-                    })
-                    .unwrap();
-                graph.add_edge(r0, r1, wall);
+            for edge in upper.graph.edge_references() {
+                grid.graph.add_edge(
+                    NodeIndex::new(first_id + edge.source().index()),
+                    NodeIndex::new(first_id + edge.target().index()),
+                    on_floor(edge.weight()),
+                );
             }
+            grid.border.extend(upper.border.iter().map(on_floor));
         }
+        grid
     }
 
-    let mut unfinished_rooms = ids_by_room.into_values().collect::<HashSet<_>>();
-    let mut rooms_in_progress = HashSet::<NodeIndex>::new();
-    let mut finished_rooms = HashSet::<NodeIndex>::new();
-
-    let mut rng = SmallRng::seed_from_u64(seed);
-    {
-        let start_room = *unfinished_rooms.iter().choose(&mut rng).unwrap();
-        unfinished_rooms.remove(&start_room);
-        rooms_in_progress.insert(start_room);
+    pub fn room_count(&self) -> usize {
+        self.graph.node_count()
     }
 
-    while let Some(room) = rooms_in_progress.iter().choose(&mut rng).copied() {
-        let Some((neighbor, wall)) = graph
-            .neighbors(room)
-            .map(|neighbor| {
-                let edge_index = graph.find_edge(room, neighbor).unwrap();
-                let wall = graph.edge_weight(edge_index).unwrap();
-                (neighbor, edge_index, wall)
+    /// Looks up the room at `(column, row)` on the ground floor.
+    pub fn room_at(&self, column: i32, row: i32) -> Option<NodeIndex> {
+        self.ids_by_room
+            .get(&Room {
+                column,
+                row,
+                floor: 0,
             })
-            .filter(|(_, _, wall)| wall.disposition == Disposition::Unknown)
-            .map(|(neighbor, edge_id, _)| (neighbor, edge_id))
-            .choose(&mut rng) else {
-                rooms_in_progress.remove(&room);
-                finished_rooms.insert(room);
-                continue;
-            };
-        let wall = graph.edge_weight_mut(wall).unwrap();
-        if unfinished_rooms.contains(&neighbor) {
-            wall.disposition = Disposition::Absent;
-            unfinished_rooms.remove(&neighbor);
-            rooms_in_progress.insert(neighbor);
-        } else {
+            .copied()
+    }
+
+    /// The rooms adjacent to `room`, each with the wall in between.
+    pub fn neighbors(&self, room: NodeIndex) -> impl Iterator<Item = (NodeIndex, EdgeIndex)> + '_ {
+        self.graph
+            .edges(room)
+            .map(move |edge| (other_end(edge.source(), edge.target(), room), edge.id()))
+    }
+
+    pub fn disposition(&self, wall: EdgeIndex) -> Disposition {
+        self.graph[wall].disposition
+    }
+
+    pub fn set_disposition(&mut self, wall: EdgeIndex, disposition: Disposition) {
+        self.graph[wall].disposition = disposition;
+    }
+
+    /// Knocks down the wall between two adjacent rooms.
+    pub fn open(&mut self, a: NodeIndex, b: NodeIndex) {
+        let wall = self
+            .graph
+            .find_edge(a, b)
+            .expect("rooms should be adjacent");
+        self.set_disposition(wall, Disposition::Absent);
+    }
+}
+
+pub fn choose_walls(spec: &MazeSpec) -> Grid {
+    let mut rng = seeded_rng(spec.seed);
+    assert!(
+        spec.algorithm.supports(spec),
+        "{} can't generate {}{} mazes",
+        spec.algorithm.name(),
+        if spec.mask.is_some() { "masked " } else { "" },
+        spec.topology.name(),
+    );
+    let floors = (0..spec.floors.max(1))
+        .map(|_| {
+            let mut grid = Grid::new(
+                spec.topology,
+                spec.x_range.clone(),
+                spec.z_range.clone(),
+                spec.mask.as_ref(),
+            );
+            if grid.room_count() > 0 {
+                spec.algorithm.carve(&mut grid, &mut rng);
+            }
+            grid
+        })
+        .collect();
+    let mut grid = Grid::stack(floors);
+
+    // Whatever the algorithm didn't knock down stays standing.
+    for wall in grid.graph.edge_weights_mut() {
+        if wall.disposition == Disposition::Unknown {
             wall.disposition = Disposition::Present;
         }
     }
 
-    assert!(unfinished_rooms.is_empty());
-    assert!(rooms_in_progress.is_empty());
+    connect_floors(&mut grid, &mut rng);
+
+    if spec.braid > 0.0 {
+        remove_dead_ends(&mut grid, spec.braid, &mut rng);
+    }
 
-    graph
+    grid
 }
 
-fn choose_spawn_positions(graph: &Graph<Room, Wall, Undirected>) -> SpawnPositions {
-    // To keep things interesting, we want to choose two rooms that are as far
-    // away as possible (in terms of path length, not Euclidean distance).
+/// The shortest distance (in room units) a ramp may cover along the floor.
+/// Much shorter, and it'd be too steep to climb.
+const MIN_RAMP_RUN: f32 = 0.7;
+
+/// Joins each floor to the next one up with a single ramp, which keeps the
+/// maze perfect if each floor was.
+fn connect_floors(grid: &mut Grid, rng: &mut MazeRng) {
+    let top_floor = grid.graph.node_weights().map(|room| room.floor).max();
+    for floor in 0..top_floor.unwrap_or(0) {
+        let mut lower_rooms = grid
+            .graph
+            .node_indices()
+            .filter(|room| grid.graph[*room].floor == floor)
+            .collect::<Vec<_>>();
+        lower_rooms.shuffle(rng);
+
+        // Look for a dead end under another dead end first.  Failing that,
+        // rearrange the upper floor around a dead end on the lower one.  And
+        // if even that fails, the floors are too small for a proper ramp, so
+        // settle for keeping the maze connected.
+        let ramp = lower_rooms
+            .iter()
+            .find_map(|room| ramp_from(grid, *room))
+            .or_else(|| {
+                lower_rooms
+                    .iter()
+                    .find_map(|room| make_room_for_ramp(grid, *room, rng))
+            })
+            .or_else(|| {
+                let (_, floor_wall) = room_above(grid, *lower_rooms.first()?)?;
+                Some(floor_wall)
+            });
+        if let Some(wall) = ramp {
+            grid.set_disposition(wall, Disposition::Absent);
+        }
+    }
+}
+
+/// The floor between `lower` and the room above it, if a ramp could climb
+/// through it as things stand.  That takes two dead ends, with their passages
+/// on different sides.
+fn ramp_from(grid: &Grid, lower: NodeIndex) -> Option<EdgeIndex> {
+    if on_ramp(&grid.graph, lower) {
+        return None;
+    }
+    let (upper, floor_wall) = room_above(grid, lower)?;
+    let entrance = only_passage(&grid.graph, lower).filter(|_| is_dead_end(grid, lower))?;
+    let exit = only_passage(&grid.graph, upper).filter(|_| is_dead_end(grid, upper))?;
+    ramp_fits(grid, lower, entrance, upper, exit).then_some(floor_wall)
+}
+
+/// Like `ramp_from`, but closes passages out of `lower` and the room above
+/// it to make them both dead ends.  Whatever each closed passage cut off gets
+/// connected to the rest of the maze some other way.
+fn make_room_for_ramp(grid: &mut Grid, lower: NodeIndex, rng: &mut MazeRng) -> Option<EdgeIndex> {
+    if on_ramp(&grid.graph, lower) {
+        return None;
+    }
+    let (upper, floor_wall) = room_above(grid, lower)?;
+    let passages = |grid: &Grid, room: NodeIndex| {
+        grid.neighbors(room)
+            .filter(|(_, wall)| grid.disposition(*wall) == Disposition::Absent)
+            .map(|(_, wall)| wall)
+            .collect::<Vec<_>>()
+    };
+    let mut ends = Vec::new();
+    for entrance in passages(grid, lower) {
+        for exit in passages(grid, upper) {
+            if ramp_fits(grid, lower, entrance, upper, exit) {
+                ends.push((entrance, exit));
+            }
+        }
+    }
+    ends.shuffle(rng);
+
+    for (entrance, exit) in ends {
+        // Every wall changed so far, and what it was before.
+        let mut changes = Vec::new();
+        if isolate(grid, lower, entrance, rng, &mut changes)
+            && isolate(grid, upper, exit, rng, &mut changes)
+        {
+            return Some(floor_wall);
+        }
+        for (wall, disposition) in changes.into_iter().rev() {
+            grid.set_disposition(wall, disposition);
+        }
+    }
+    None
+}
 
-    let distances = floyd_warshall(graph, |edge_ref| {
-        if edge_ref.weight().disposition == Disposition::Absent {
-            1
+/// Closes every passage out of `room` except `keep`, and opens another wall
+/// to reconnect whatever each one cut off.  Records every wall it changes in
+/// `changes`, and gives up if something can't be reconnected.
+fn isolate(
+    grid: &mut Grid,
+    room: NodeIndex,
+    keep: EdgeIndex,
+    rng: &mut MazeRng,
+    changes: &mut Vec<(EdgeIndex, Disposition)>,
+) -> bool {
+    let passages = grid
+        .neighbors(room)
+        .filter(|(_, wall)| *wall != keep && grid.disposition(*wall) == Disposition::Absent)
+        .collect::<Vec<_>>();
+    for (neighbor, passage) in passages {
+        // Rooms on a ramp have to keep the passage they have.
+        if on_ramp(&grid.graph, neighbor) {
+            return false;
+        }
+        grid.set_disposition(passage, Disposition::Present);
+        changes.push((passage, Disposition::Absent));
+
+        let cut_off = passage_distances(&grid.graph, neighbor);
+        let bridges = grid
+            .graph
+            .node_indices()
+            .filter(|cut_off_room| {
+                cut_off[cut_off_room.index()].is_some() && !on_ramp(&grid.graph, *cut_off_room)
+            })
+            .flat_map(|cut_off_room| grid.neighbors(cut_off_room))
+            .filter(|(other, wall)| {
+                cut_off[other.index()].is_none()
+                    && *other != room
+                    && !on_ramp(&grid.graph, *other)
+                    && grid.disposition(*wall) == Disposition::Present
+                    && !grid.graph[*wall].side.is_vertical()
+            })
+            .map(|(_, wall)| wall)
+            .collect::<Vec<_>>();
+        let Some(&bridge) = bridges.choose(rng) else {
+            return false;
+        };
+        grid.set_disposition(bridge, Disposition::Absent);
+        changes.push((bridge, Disposition::Present));
+    }
+    true
+}
+
+/// The room above `room`, and the floor between them.
+fn room_above(grid: &Grid, room: NodeIndex) -> Option<(NodeIndex, EdgeIndex)> {
+    let floor = grid.graph[room].floor;
+    grid.neighbors(room)
+        .find(|(neighbor, _)| grid.graph[*neighbor].floor > floor)
+}
+
+fn is_dead_end(grid: &Grid, room: NodeIndex) -> bool {
+    grid.neighbors(room)
+        .filter(|(_, wall)| grid.disposition(*wall) == Disposition::Absent)
+        .count()
+        == 1
+}
+
+/// Whether a ramp could climb from `lower`'s `entrance` to `upper`'s `exit`
+/// without being too steep, or running along a wall.
+fn ramp_fits(
+    grid: &Grid,
+    lower: NodeIndex,
+    entrance: EdgeIndex,
+    upper: NodeIndex,
+    exit: EdgeIndex,
+) -> bool {
+    let side_of = |room: NodeIndex, wall: &Wall| {
+        if wall.room == grid.graph[room] {
+            wall.side
         } else {
-            i32::MAX
+            wall.side.opposite()
         }
+    };
+    let (entrance, exit) = (&grid.graph[entrance], &grid.graph[exit]);
+    if side_of(lower, entrance) == side_of(upper, exit) {
+        return false;
+    }
+    let (x0, z0) = grid.topology.wall_midpoint(entrance);
+    let (x1, z1) = grid.topology.wall_midpoint(exit);
+    (x1 - x0).hypot(z1 - z0) >= MIN_RAMP_RUN
+}
+
+/// The only passage out of `room` along its floor, if it has exactly one.
+fn only_passage(graph: &Graph<Room, Wall, Undirected>, room: NodeIndex) -> Option<EdgeIndex> {
+    let mut passages = graph.edges(room).filter(|edge| {
+        edge.weight().disposition == Disposition::Absent && !edge.weight().side.is_vertical()
+    });
+    let passage = passages.next()?;
+    passages.next().is_none().then_some(passage.id())
+}
+
+/// Whether a ramp starts or ends in `room`.
+fn on_ramp(graph: &Graph<Room, Wall, Undirected>, room: NodeIndex) -> bool {
+    graph.edges(room).any(|edge| {
+        edge.weight().disposition == Disposition::Absent && edge.weight().side.is_vertical()
     })
-    .unwrap();
-    let ((start_index, goal_index), _distance) =
-        distances.iter().max_by_key(|item| *item.1).unwrap();
-    let start = *graph.node_weight(*start_index).unwrap();
-    let goal = *graph.node_weight(*goal_index).unwrap();
+}
+
+/// The generator for a maze with the given seed.  It starts out in the same
+/// state `SmallRng` did on 64-bit targets, but the algorithms now draw from
+/// it differently, so older seeds give different mazes than they used to.
+/// The golden files in `tests/golden/` lock in the mazes from here on.
+fn seeded_rng(seed: u64) -> MazeRng {
+    // `SmallRng` used `SeedableRng::seed_from_u64`'s default, which stretches
+    // the seed with PCG32, but `Xoshiro256PlusPlus` overrides that with
+    // SplitMix64.  So stretch the seed on its own first.
+    struct Seed([u8; 32]);
+    impl SeedableRng for Seed {
+        type Seed = [u8; 32];
+        fn from_seed(seed: [u8; 32]) -> Self {
+            Seed(seed)
+        }
+    }
+    MazeRng::from_seed(Seed::seed_from_u64(seed).0)
+}
+
+/// Knocks down walls until only `1.0 - braid` of the dead ends are left.
+fn remove_dead_ends(grid: &mut Grid, braid: f32, rng: &mut MazeRng) {
+    let open_walls = |grid: &Grid, room: NodeIndex| {
+        grid.neighbors(room)
+            .filter(|(_, wall)| grid.disposition(*wall) == Disposition::Absent)
+            .count()
+    };
+
+    let mut dead_ends = grid
+        .graph
+        .node_indices()
+        .filter(|room| open_walls(grid, *room) == 1 && !on_ramp(&grid.graph, *room))
+        .collect::<Vec<_>>();
+    let keep = ((1.0 - braid.clamp(0.0, 1.0)) * dead_ends.len() as f32).round() as usize;
+    let mut remaining = dead_ends.len();
+    dead_ends.shuffle(rng);
+
+    for room in dead_ends {
+        if remaining <= keep {
+            break;
+        }
+        // Opening an earlier dead end may already have fixed this one.
+        if open_walls(grid, room) != 1 {
+            continue;
+        }
+        // Joining two dead ends together fixes both at once, which keeps the
+        // extra loops to a minimum.  Ramps need their rooms to keep a single
+        // passage, so leave those alone.
+        let closed = grid
+            .neighbors(room)
+            .filter(|(neighbor, wall)| {
+                grid.disposition(*wall) == Disposition::Present
+                    && !grid.graph[*wall].side.is_vertical()
+                    && !on_ramp(&grid.graph, *neighbor)
+            })
+            .collect::<Vec<_>>();
+        let dead_end_neighbors = closed
+            .iter()
+            .filter(|(neighbor, _)| open_walls(grid, *neighbor) == 1)
+            .collect::<Vec<_>>();
+        let Some(&(neighbor, wall)) = dead_end_neighbors
+            .choose(rng)
+            .copied()
+            .or_else(|| closed.choose(rng))
+        else {
+            continue;
+        };
+        grid.set_disposition(wall, Disposition::Absent);
+        remaining -= 1;
+        if open_walls(grid, neighbor) == 2 {
+            remaining -= 1;
+        }
+    }
+}
+
+fn other_end(source: NodeIndex, target: NodeIndex, this_end: NodeIndex) -> NodeIndex {
+    if source == this_end {
+        target
+    } else {
+        source
+    }
+}
+
+/// Picks a start and a goal as far apart by path length as any two rooms.
+pub fn choose_spawn_positions(graph: &Graph<Room, Wall, Undirected>) -> SpawnPositions {
+    // To keep things interesting, we want to choose two rooms that are as far
+    // away as possible (in terms of path length, not Euclidean distance).
+    //
+    // Without braiding, `choose_walls` carves a spanning tree, so this is the
+    // tree's diameter: the room farthest from an arbitrary room is one end of
+    // a longest path, and the room farthest from *that* room is the other
+    // end.  Two BFS passes keep this linear in the number of rooms.
+    //
+    // A braided maze has loops, and then those two passes can fall short, so
+    // `farthest_pair` makes sure.
+
+    let (start_index, _) = farthest_room(graph, NodeIndex::new(0));
+    let (goal_index, _) = farthest_room(graph, start_index);
+    let passages = graph
+        .edge_weights()
+        .filter(|wall| wall.disposition == Disposition::Absent)
+        .count();
+    let (start_index, goal_index) = if passages < graph.node_count() {
+        (start_index, goal_index)
+    } else {
+        farthest_pair(graph, start_index, goal_index)
+    };
+    let start = *graph.node_weight(start_index).unwrap();
+    let goal = *graph.node_weight(goal_index).unwrap();
     SpawnPositions { start, goal }
 }
 
-fn build_walls(
-    commands: &mut ChildBuilder,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    x_range: RangeInclusive<i32>,
-    z_range: RangeInclusive<i32>,
-    walls: impl Iterator<Item = Wall>,
-    sizes: &Sizes,
-) {
-    let (x_min, x_max) = (*x_range.start(), *x_range.end());
-    let (z_min, z_max) = (*z_range.start(), *z_range.end());
-
-    let corner_mesh = meshes.add(
-        shape::Cylinder {
-            radius: sizes.wall_radius,
-            height: sizes.room_height,
-            resolution: 8,
-            segments: 1,
+/// Finds two rooms as far apart as any in a maze with loops, given the ends
+/// of a long path through it.  This is the iFUB algorithm (Crescenzi et al.,
+/// "On computing the diameter of real-world undirected graphs"): it searches
+/// from a room halfway along the path, and then from every other room,
+/// farthest first, until the rooms left are too close to the halfway room
+/// to be twice as far from each other as the best pair so far.  That's
+/// exact, and in practice takes a handful of passes rather than one per room.
+fn farthest_pair(
+    graph: &Graph<Room, Wall, Undirected>,
+    a: NodeIndex,
+    b: NodeIndex,
+) -> (NodeIndex, NodeIndex) {
+    let from_a = passage_distances(graph, a);
+    let from_b = passage_distances(graph, b);
+    let length = from_a[b.index()].unwrap();
+    let middle = (0..graph.node_count())
+        .find(|index| {
+            from_a[*index] == Some(length / 2) && from_b[*index] == Some(length - length / 2)
+        })
+        .map(NodeIndex::new)
+        .unwrap();
+
+    // `levels[d]` lists the rooms `d` steps from the middle.
+    let mut levels = Vec::<Vec<NodeIndex>>::new();
+    for (index, distance) in passage_distances(graph, middle).into_iter().enumerate() {
+        let Some(distance) = distance else {
+            continue;
+        };
+        if levels.len() <= distance {
+            levels.resize(distance + 1, Vec::new());
         }
-        .into(),
-    );
-    let corner_material = materials.add(Color::BLUE.into());
-
-    let wall_mesh = meshes.add(
-        shape::Box {
-            min_x: -sizes.room_side_length / 2.0,
-            max_x: sizes.room_side_length / 2.0,
-            min_y: 0.0,
-            max_y: sizes.room_height,
-            min_z: -sizes.wall_radius,
-            max_z: sizes.wall_radius,
+        levels[distance].push(NodeIndex::new(index));
+    }
+    let mut best = (a, b, length);
+    for (level, rooms) in levels.iter().enumerate().rev() {
+        // Rooms within `level` steps of the middle are within `2 * level`
+        // steps of each other, and every farther room has been searched from
+        // already.
+        if best.2 >= 2 * level {
+            break;
         }
-        .into(),
-    );
-    let wall_material = materials.add(Color::BLUE.into());
-
-    for wall in walls.filter(|w| w.disposition == Disposition::Present) {
-        let mut transform = Transform::from_xyz(
-            wall.sw_corner.0 as f32 * sizes.room_side_length,
-            0.0,
-            wall.sw_corner.1 as f32 * sizes.room_side_length,
-        );
-        match wall.orientation {
-            WallOrientation::ParallelToX => {
-                transform.translation += Vec3::X * sizes.room_side_length * 0.5;
-            }
-            WallOrientation::ParallelToZ => {
-                transform.translation += Vec3::Z * sizes.room_side_length * 0.5;
-                transform.rotate_y(TAU / 4.0);
+        for &room in rooms {
+            let (far, distance) = farthest_room(graph, room);
+            if distance > best.2 {
+                best = (room, far, distance);
             }
         }
+    }
+    (best.0, best.1)
+}
 
-        commands
-            .spawn((PbrBundle {
-                mesh: wall_mesh.clone(),
-                material: wall_material.clone(),
-                transform,
-                ..default()
-            },))
-            .with_children(|commands| {
-                // The collider `cuboid` primitive is always *centered* at the origin,
-                // but the mesh above puts the origin at the *bottom* of the wall.
-                commands.spawn((
-                    Collider::cuboid(
-                        sizes.room_side_length / 2.0,
-                        sizes.room_height / 2.0,
-                        sizes.wall_radius,
-                    ),
-                    SpatialBundle {
-                        transform: Transform::from_translation(Vec3::Y * sizes.room_height / 2.0),
-                        ..default()
-                    },
-                ));
-            });
+/// Puts the goal in the center of a polar maze, and the start at whichever
+/// room on the rim is farthest from it.  If a mask took out the center,
+/// falls back on `choose_spawn_positions`.
+fn choose_polar_spawn_positions(grid: &Grid) -> SpawnPositions {
+    let Some(goal_index) = grid.room_at(0, 0) else {
+        return choose_spawn_positions(&grid.graph);
+    };
+    let rim = grid
+        .graph
+        .node_weights()
+        .map(|room| room.row)
+        .max()
+        .unwrap();
+    let (start_index, _) = passage_distances(&grid.graph, goal_index)
+        .into_iter()
+        .enumerate()
+        .map(|(index, distance)| (NodeIndex::new(index), distance))
+        .filter(|(index, _)| grid.graph[*index].row == rim)
+        .max_by_key(|(_, distance)| *distance)
+        .unwrap();
+    SpawnPositions {
+        start: grid.graph[start_index],
+        goal: grid.graph[goal_index],
     }
+}
 
-    // Spawn corner columns
-    for x in x_min..=x_max {
-        for z in z_min..=z_max {
-            commands.spawn((
-                PbrBundle {
-                    mesh: corner_mesh.clone(),
-                    material: corner_material.clone(),
-                    transform: Transform::from_xyz(
-                        x as f32 * sizes.room_side_length,
-                        sizes.room_height / 2.0,
-                        z as f32 * sizes.room_side_length,
-                    ),
-                    ..default()
-                },
-                Collider::cylinder(sizes.room_height / 2.0, sizes.wall_radius),
-            ));
+/// Finds the room with the longest path from `origin`, and that path's length.
+fn farthest_room(graph: &Graph<Room, Wall, Undirected>, origin: NodeIndex) -> (NodeIndex, usize) {
+    passage_distances(graph, origin)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, distance)| Some((NodeIndex::new(index), distance?)))
+        .max_by_key(|(_, distance)| *distance)
+        .unwrap()
+}
+
+/// Counts the steps from `origin` to every room, moving only through absent
+/// walls.  Rooms that can't be reached at all are `None`.
+fn passage_distances(
+    graph: &Graph<Room, Wall, Undirected>,
+    origin: NodeIndex,
+) -> Vec<Option<usize>> {
+    let mut distances = vec![None; graph.node_count()];
+    let mut frontier = VecDeque::from([origin]);
+    distances[origin.index()] = Some(0);
+    while let Some(room) = frontier.pop_front() {
+        let distance = distances[room.index()].unwrap();
+        for edge in graph.edges(room) {
+            if edge.weight().disposition != Disposition::Absent {
+                continue;
+            }
+            let neighbor = other_end(edge.source(), edge.target(), room);
+            if distances[neighbor.index()].is_none() {
+                distances[neighbor.index()] = Some(distance + 1);
+                frontier.push_back(neighbor);
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn braided_spawn_positions_are_as_far_apart_as_any_rooms() {
+        for topology in [Topology::Square, Topology::Hex] {
+            for seed in 0..20 {
+                let spec = MazeSpec {
+                    topology,
+                    x_range: -4..=4,
+                    z_range: -3..=4,
+                    mask: None,
+                    floors: 1,
+                    algorithm: Algorithm::default(),
+                    braid: 0.5,
+                    seed,
+                };
+                let layout = MazeLayout::generate(&spec).unwrap();
+                let id = |room: Room| {
+                    layout
+                        .graph
+                        .node_indices()
+                        .find(|id| layout.graph[*id] == room)
+                        .unwrap()
+                };
+                let distance = passage_distances(&layout.graph, id(layout.start))
+                    [id(layout.goal).index()]
+                .unwrap();
+                let farthest = layout
+                    .graph
+                    .node_indices()
+                    .map(|room| farthest_room(&layout.graph, room).1)
+                    .max()
+                    .unwrap();
+                assert_eq!(distance, farthest, "{topology:?} with seed {seed}");
+            }
         }
     }
 }
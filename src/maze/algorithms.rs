@@ -0,0 +1,215 @@
+//! Different ways of carving passages into a [`Grid`].
+//!
+//! Every algorithm produces a perfect maze (exactly one path between any two
+//! rooms), but each leaves a different texture: long winding corridors, lots
+//! of short dead ends, a bias towards one corner, and so on.
+
+use std::{fmt, mem::discriminant};
+
+use petgraph::graph::NodeIndex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{Grid, MazeRng, MazeSpec};
+
+mod aldous_broder;
+mod binary_tree;
+mod eller;
+mod growing_tree;
+mod hunt_and_kill;
+mod kruskal;
+mod prim;
+mod recursive_backtracker;
+mod recursive_division;
+mod sidewinder;
+mod wilson;
+
+pub use aldous_broder::AldousBroder;
+pub use binary_tree::BinaryTree;
+pub use eller::Eller;
+pub use growing_tree::{GrowingTree, Selection};
+pub use hunt_and_kill::HuntAndKill;
+pub use kruskal::Kruskal;
+pub use prim::Prim;
+pub use recursive_backtracker::RecursiveBacktracker;
+pub use recursive_division::RecursiveDivision;
+pub use sidewinder::Sidewinder;
+pub use wilson::Wilson;
+
+pub trait MazeAlgorithm {
+    /// Knocks down walls in `grid` (by marking them `Disposition::Absent`) until
+    /// every room is reachable from every other room.  Walls left `Unknown`
+    /// are treated as `Present` afterwards.
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng);
+
+    /// Whether this algorithm can carve the grid `spec` describes.
+    fn supports(&self, _spec: &MazeSpec) -> bool {
+        true
+    }
+}
+
+/// Every built-in [`MazeAlgorithm`], as a value that's easy to store and pass
+/// around.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    GrowingTree(Selection),
+    RecursiveBacktracker,
+    Kruskal,
+    Prim,
+    Wilson,
+    AldousBroder,
+    Eller,
+    HuntAndKill,
+    Sidewinder,
+    BinaryTree,
+    RecursiveDivision,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 11] = [
+        Algorithm::GrowingTree(Selection::RANDOM),
+        Algorithm::RecursiveBacktracker,
+        Algorithm::Kruskal,
+        Algorithm::Prim,
+        Algorithm::Wilson,
+        Algorithm::AldousBroder,
+        Algorithm::Eller,
+        Algorithm::HuntAndKill,
+        Algorithm::Sidewinder,
+        Algorithm::BinaryTree,
+        Algorithm::RecursiveDivision,
+    ];
+
+    /// A human-readable name, for menus and the HUD.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::GrowingTree(_) => "Growing tree",
+            Algorithm::RecursiveBacktracker => "Recursive backtracker",
+            Algorithm::Kruskal => "Kruskal",
+            Algorithm::Prim => "Prim",
+            Algorithm::Wilson => "Wilson",
+            Algorithm::AldousBroder => "Aldous-Broder",
+            Algorithm::Eller => "Eller",
+            Algorithm::HuntAndKill => "Hunt-and-kill",
+            Algorithm::Sidewinder => "Sidewinder",
+            Algorithm::BinaryTree => "Binary tree",
+            Algorithm::RecursiveDivision => "Recursive division",
+        }
+    }
+
+    /// The algorithm after this one in [`Algorithm::ALL`], wrapping around.
+    /// (Parameters such as the growing tree's selection policy don't matter.)
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|a| discriminant(a) == discriminant(&self))
+            .unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Algorithm::GrowingTree(Selection::default())
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Algorithm::GrowingTree(selection) => write!(f, "{} ({selection})", self.name()),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl MazeAlgorithm for Algorithm {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        match self {
+            Algorithm::GrowingTree(selection) => GrowingTree {
+                selection: *selection,
+            }
+            .carve(grid, rng),
+            Algorithm::RecursiveBacktracker => RecursiveBacktracker.carve(grid, rng),
+            Algorithm::Kruskal => Kruskal.carve(grid, rng),
+            Algorithm::Prim => Prim.carve(grid, rng),
+            Algorithm::Wilson => Wilson.carve(grid, rng),
+            Algorithm::AldousBroder => AldousBroder.carve(grid, rng),
+            Algorithm::Eller => Eller.carve(grid, rng),
+            Algorithm::HuntAndKill => HuntAndKill.carve(grid, rng),
+            Algorithm::Sidewinder => Sidewinder.carve(grid, rng),
+            Algorithm::BinaryTree => BinaryTree.carve(grid, rng),
+            Algorithm::RecursiveDivision => RecursiveDivision.carve(grid, rng),
+        }
+    }
+
+    fn supports(&self, spec: &MazeSpec) -> bool {
+        match self {
+            Algorithm::Eller => Eller.supports(spec),
+            Algorithm::Sidewinder => Sidewinder.supports(spec),
+            Algorithm::BinaryTree => BinaryTree.supports(spec),
+            Algorithm::RecursiveDivision => RecursiveDivision.supports(spec),
+            _ => true,
+        }
+    }
+}
+
+fn random_room(grid: &Grid, rng: &mut MazeRng) -> NodeIndex {
+    NodeIndex::new(random_index(rng, grid.room_count()))
+}
+
+/// A random index into a list of `len` things.  `gen_range` over `usize`
+/// draws 32 bits at a time in the browser but 64 on native builds, which
+/// would give the same seed different mazes; drawing a `u64` everywhere
+/// matches the native builds.
+fn random_index(rng: &mut MazeRng, len: usize) -> usize {
+    rng.gen_range(0..len as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::{unionfind::UnionFind, visit::EdgeRef};
+
+    use super::*;
+    use crate::maze::{Disposition, MazeLayout, Topology};
+
+    #[test]
+    fn every_algorithm_carves_a_perfect_maze() {
+        for algorithm in Algorithm::ALL {
+            for topology in Topology::ALL {
+                for floors in [1, 2] {
+                    let spec = MazeSpec {
+                        topology,
+                        x_range: -4..=4,
+                        z_range: match topology {
+                            Topology::Polar => 0..=5,
+                            _ => -3..=4,
+                        },
+                        mask: None,
+                        floors,
+                        algorithm,
+                        braid: 0.0,
+                        seed: 0x5eed_f00d,
+                    };
+                    if !algorithm.supports(&spec) {
+                        continue;
+                    }
+                    let case = format!("{} on {floors} {topology:?} floor(s)", algorithm.name());
+                    let graph = MazeLayout::generate(&spec).unwrap().graph;
+
+                    // A cycle would join two rooms that are already joined.
+                    let mut joined = UnionFind::<usize>::new(graph.node_count());
+                    let mut passages = 0;
+                    for edge in graph.edge_references() {
+                        if edge.weight().disposition == Disposition::Absent {
+                            let (a, b) = (edge.source().index(), edge.target().index());
+                            assert!(joined.union(a, b), "{case} has a loop");
+                            passages += 1;
+                        }
+                    }
+                    assert_eq!(passages, graph.node_count() - 1, "{case} isn't connected");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use rand::seq::IteratorRandom;
+
+use super::{random_room, MazeAlgorithm};
+use crate::maze::{Disposition, Grid, MazeRng};
+
+/// Aldous-Broder: wanders the grid at random, knocking down a wall whenever
+/// it steps into a room for the first time.  Every possible maze is equally
+/// likely, but it can take a long time to find the last few rooms.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AldousBroder;
+
+impl MazeAlgorithm for AldousBroder {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let mut visited = vec![false; grid.room_count()];
+        let mut room = random_room(grid, rng);
+        visited[room.index()] = true;
+
+        let mut remaining = grid.room_count() - 1;
+        while remaining > 0 {
+            let (neighbor, wall) = grid.neighbors(room).choose(rng).unwrap();
+            if !visited[neighbor.index()] {
+                grid.set_disposition(wall, Disposition::Absent);
+                visited[neighbor.index()] = true;
+                remaining -= 1;
+            }
+            room = neighbor;
+        }
+    }
+}
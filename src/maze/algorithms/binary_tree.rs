@@ -0,0 +1,36 @@
+use rand::seq::SliceRandom;
+
+use super::MazeAlgorithm;
+use crate::maze::{Grid, MazeRng, MazeSpec, Topology};
+
+/// Binary tree: every room opens either its north or its east wall.  Very
+/// fast, but leaves unbroken corridors along the north and east edges and a
+/// strong diagonal bias.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BinaryTree;
+
+impl MazeAlgorithm for BinaryTree {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let (x_min, x_max) = (*grid.x_range.start(), *grid.x_range.end());
+        let (z_min, z_max) = (*grid.z_range.start(), *grid.z_range.end());
+
+        for x in x_min..x_max {
+            for z in z_min..z_max {
+                let room = grid.room_at(x, z).unwrap();
+                let candidates = [grid.room_at(x, z + 1), grid.room_at(x + 1, z)];
+                let candidates = candidates.iter().flatten().copied().collect::<Vec<_>>();
+                if let Some(&neighbor) = candidates.choose(rng) {
+                    grid.open(room, neighbor);
+                }
+            }
+        }
+    }
+
+    /// Polar rings don't line up in columns, so there's no room "north" of
+    /// most rooms in the `(column, row)` sense this relies on.  Masks cause
+    /// the same trouble: a room with a hole to its north and east would be
+    /// cut off.
+    fn supports(&self, spec: &MazeSpec) -> bool {
+        spec.topology != Topology::Polar && spec.mask.is_none()
+    }
+}
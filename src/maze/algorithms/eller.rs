@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use rand::{seq::SliceRandom, Rng};
+
+use super::{random_index, MazeAlgorithm};
+use crate::maze::{Grid, MazeRng, MazeSpec, Topology};
+
+/// Eller's algorithm: works one row at a time, only ever remembering which
+/// rooms of the current row are already connected.  Randomly joins
+/// neighbors within a row, then drops at least one passage north from each
+/// connected set.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Eller;
+
+impl MazeAlgorithm for Eller {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let (x_min, x_max) = (*grid.x_range.start(), *grid.x_range.end());
+        let (z_min, z_max) = (*grid.z_range.start(), *grid.z_range.end());
+        let width = (x_max - x_min) as usize;
+
+        // Which connected set each room of the current row belongs to.
+        let mut sets = vec![None::<usize>; width];
+        let mut next_set = 0;
+
+        for z in z_min..z_max {
+            let last_row = z + 1 == z_max;
+            for set in &mut sets {
+                if set.is_none() {
+                    *set = Some(next_set);
+                    next_set += 1;
+                }
+            }
+
+            // Join neighbors within the row.  The last row has to join
+            // everything that isn't connected yet.
+            for column in 1..width {
+                let (west, east) = (sets[column - 1], sets[column]);
+                if west != east && (last_row || rng.gen_bool(0.5)) {
+                    let x = x_min + column as i32;
+                    grid.open(grid.room_at(x - 1, z).unwrap(), grid.room_at(x, z).unwrap());
+                    for set in &mut sets {
+                        if *set == east {
+                            *set = west;
+                        }
+                    }
+                }
+            }
+            if last_row {
+                break;
+            }
+
+            // Every set continues north through at least one of its rooms.
+            // (A `BTreeMap` keeps the order of the random choices below
+            // independent of hashing.)
+            let mut columns_by_set = BTreeMap::<usize, Vec<usize>>::new();
+            for (column, set) in sets.iter().enumerate() {
+                columns_by_set.entry(set.unwrap()).or_default().push(column);
+            }
+            let mut next_sets = vec![None; width];
+            for (set, mut columns) in columns_by_set {
+                columns.shuffle(rng);
+                let passages = 1 + random_index(rng, columns.len());
+                for &column in &columns[..passages] {
+                    let x = x_min + column as i32;
+                    grid.open(grid.room_at(x, z).unwrap(), grid.room_at(x, z + 1).unwrap());
+                    next_sets[column] = Some(set);
+                }
+            }
+            sets = next_sets;
+        }
+    }
+
+    /// Polar rings have different numbers of rooms, so there's no fixed-width
+    /// row to carry the connected sets between.  Nor is there with a mask,
+    /// which can break rows into pieces.
+    fn supports(&self, spec: &MazeSpec) -> bool {
+        spec.topology != Topology::Polar && spec.mask.is_none()
+    }
+}
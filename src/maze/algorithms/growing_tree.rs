@@ -0,0 +1,205 @@
+use std::fmt;
+
+use petgraph::graph::NodeIndex;
+use rand::{seq::IteratorRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use super::{random_index, random_room, MazeAlgorithm};
+use crate::maze::{Disposition, Grid, MazeRng};
+
+/// Grows the maze outward from a single room.  Each step extends a room from
+/// the list of rooms that still have uncarved neighbors; which room gets
+/// picked is up to the [`Selection`] policy.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct GrowingTree {
+    pub selection: Selection,
+}
+
+/// How [`GrowingTree`] picks the next room to extend from its in-progress
+/// list, as relative weights.  Always taking the newest room behaves like the
+/// recursive backtracker (long corridors, few branches); always taking a
+/// random one behaves like Prim's algorithm (short corridors, many branches).
+/// Mixing the two tunes between those extremes.  At least one weight is
+/// nonzero, so there's always a room to pick.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Weights")]
+pub struct Selection {
+    /// The room most recently added to the list.
+    newest: u8,
+    /// The room that has been on the list longest.
+    oldest: u8,
+    /// The room halfway down the list.
+    middle: u8,
+    /// Any room on the list.
+    random: u8,
+}
+
+/// A [`Selection`] as saved, before it's checked for a nonzero weight.
+#[derive(Deserialize)]
+struct Weights {
+    newest: u8,
+    oldest: u8,
+    middle: u8,
+    random: u8,
+}
+
+impl TryFrom<Weights> for Selection {
+    type Error = &'static str;
+
+    fn try_from(weights: Weights) -> Result<Self, Self::Error> {
+        let Weights {
+            newest,
+            oldest,
+            middle,
+            random,
+        } = weights;
+        Selection::new(newest, oldest, middle, random)
+            .ok_or("a selection policy needs at least one nonzero weight")
+    }
+}
+
+impl Selection {
+    pub const NEWEST: Selection = Selection::only(1, 0, 0, 0);
+    pub const OLDEST: Selection = Selection::only(0, 1, 0, 0);
+    pub const MIDDLE: Selection = Selection::only(0, 0, 1, 0);
+    pub const RANDOM: Selection = Selection::only(0, 0, 0, 1);
+
+    /// A few useful policies, for cycling through in game.
+    pub const PRESETS: [Selection; 6] = [
+        Selection::RANDOM,
+        Selection::NEWEST,
+        Selection::only(75, 0, 0, 25),
+        Selection::only(50, 0, 0, 50),
+        Selection::MIDDLE,
+        Selection::OLDEST,
+    ];
+
+    /// A policy with the given weights, unless they're all zero.
+    pub fn new(newest: u8, oldest: u8, middle: u8, random: u8) -> Option<Self> {
+        let selection = Selection::only(newest, oldest, middle, random);
+        (selection.total() > 0).then_some(selection)
+    }
+
+    const fn only(newest: u8, oldest: u8, middle: u8, random: u8) -> Self {
+        Selection {
+            newest,
+            oldest,
+            middle,
+            random,
+        }
+    }
+
+    /// The preset after this one in [`Selection::PRESETS`], wrapping around.
+    /// Custom policies move to the first preset.
+    pub fn next_preset(self) -> Self {
+        let index = Self::PRESETS.iter().position(|s| *s == self);
+        Self::PRESETS[index.map_or(0, |i| (i + 1) % Self::PRESETS.len())]
+    }
+
+    fn weights(&self) -> [(u8, &'static str); 4] {
+        [
+            (self.newest, "newest"),
+            (self.oldest, "oldest"),
+            (self.middle, "middle"),
+            (self.random, "random"),
+        ]
+    }
+
+    fn total(&self) -> u32 {
+        self.weights().iter().map(|(w, _)| *w as u32).sum()
+    }
+
+    /// Whether only random picks are made, so the list's order never comes
+    /// into it.
+    fn ignores_order(&self) -> bool {
+        self.total() == self.random as u32
+    }
+
+    /// Chooses a position in a list of `len` rooms, ordered oldest first.
+    fn pick(&self, len: usize, rng: &mut MazeRng) -> usize {
+        let mut roll = rng.gen_range(0..self.total());
+        let mut choice = 3;
+        for (index, (weight, _)) in self.weights().into_iter().enumerate() {
+            if roll < weight as u32 {
+                choice = index;
+                break;
+            }
+            roll -= weight as u32;
+        }
+        match choice {
+            0 => len - 1,
+            1 => 0,
+            2 => len / 2,
+            _ => random_index(rng, len),
+        }
+    }
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection::RANDOM
+    }
+}
+
+impl fmt::Display for Selection {
+    /// Formats as e.g. "newest" or "75% newest / 25% random".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let weights = self
+            .weights()
+            .into_iter()
+            .filter(|(weight, _)| *weight > 0)
+            .collect::<Vec<_>>();
+        let total = weights.iter().map(|(w, _)| *w as u32).sum::<u32>();
+        if let [(_, name)] = weights[..] {
+            return write!(f, "{name}");
+        }
+        for (index, (weight, name)) in weights.into_iter().enumerate() {
+            if index > 0 {
+                write!(f, " / ")?;
+            }
+            write!(f, "{}% {name}", weight as u32 * 100 / total)?;
+        }
+        Ok(())
+    }
+}
+
+impl MazeAlgorithm for GrowingTree {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let mut unfinished_rooms = vec![true; grid.room_count()];
+        let mut rooms_in_progress = Vec::<NodeIndex>::new();
+
+        {
+            let start_room = random_room(grid, rng);
+            unfinished_rooms[start_room.index()] = false;
+            rooms_in_progress.push(start_room);
+        }
+
+        while !rooms_in_progress.is_empty() {
+            let position = self.selection.pick(rooms_in_progress.len(), rng);
+            let room = rooms_in_progress[position];
+            let Some((neighbor, wall)) = grid
+                .neighbors(room)
+                .filter(|(_, wall)| grid.disposition(*wall) == Disposition::Unknown)
+                .choose(rng)
+            else {
+                // Keeping the list in order is only worth its linear cost
+                // when the policy looks at the order.
+                if self.selection.ignores_order() {
+                    rooms_in_progress.swap_remove(position);
+                } else {
+                    rooms_in_progress.remove(position);
+                }
+                continue;
+            };
+            if unfinished_rooms[neighbor.index()] {
+                grid.set_disposition(wall, Disposition::Absent);
+                unfinished_rooms[neighbor.index()] = false;
+                rooms_in_progress.push(neighbor);
+            } else {
+                grid.set_disposition(wall, Disposition::Present);
+            }
+        }
+
+        assert!(!unfinished_rooms.contains(&true));
+    }
+}
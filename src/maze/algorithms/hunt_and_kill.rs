@@ -0,0 +1,58 @@
+use rand::seq::IteratorRandom;
+
+use super::{random_room, MazeAlgorithm};
+use crate::maze::{Disposition, Grid, MazeRng};
+
+/// Hunt-and-kill: walks randomly into unvisited rooms like the recursive
+/// backtracker, but at a dead end it "hunts" for the first unvisited room
+/// next to the maze instead of backing up.  Makes long corridors with fewer
+/// dead ends.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct HuntAndKill;
+
+impl MazeAlgorithm for HuntAndKill {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let mut visited = vec![false; grid.room_count()];
+        let mut room = random_room(grid, rng);
+        visited[room.index()] = true;
+
+        // Every room before `hunt_from` is known to be visited, so each hunt
+        // can skip them.
+        let mut hunt_from = 0;
+        loop {
+            // Kill
+            if let Some((neighbor, wall)) = grid
+                .neighbors(room)
+                .filter(|(neighbor, _)| !visited[neighbor.index()])
+                .choose(rng)
+            {
+                grid.set_disposition(wall, Disposition::Absent);
+                visited[neighbor.index()] = true;
+                room = neighbor;
+                continue;
+            }
+
+            // Hunt
+            while hunt_from < visited.len() && visited[hunt_from] {
+                hunt_from += 1;
+            }
+            let found = grid
+                .graph
+                .node_indices()
+                .skip(hunt_from)
+                .filter(|candidate| !visited[candidate.index()])
+                .find_map(|candidate| {
+                    grid.neighbors(candidate)
+                        .filter(|(neighbor, _)| visited[neighbor.index()])
+                        .choose(rng)
+                        .map(|(_, wall)| (candidate, wall))
+                });
+            let Some((candidate, wall)) = found else {
+                break;
+            };
+            grid.set_disposition(wall, Disposition::Absent);
+            visited[candidate.index()] = true;
+            room = candidate;
+        }
+    }
+}
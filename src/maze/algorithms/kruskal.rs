@@ -0,0 +1,29 @@
+use petgraph::{unionfind::UnionFind, visit::EdgeRef};
+use rand::seq::SliceRandom;
+
+use super::MazeAlgorithm;
+use crate::maze::{Disposition, Grid, MazeRng};
+
+/// Randomized Kruskal's algorithm: visits every wall in random order, and
+/// knocks it down if the rooms on either side aren't connected yet.  Makes
+/// lots of short dead ends.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Kruskal;
+
+impl MazeAlgorithm for Kruskal {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let mut walls = grid
+            .graph
+            .edge_references()
+            .map(|edge| (edge.id(), edge.source(), edge.target()))
+            .collect::<Vec<_>>();
+        walls.shuffle(rng);
+
+        let mut regions = UnionFind::<usize>::new(grid.room_count());
+        for (wall, a, b) in walls {
+            if regions.union(a.index(), b.index()) {
+                grid.set_disposition(wall, Disposition::Absent);
+            }
+        }
+    }
+}
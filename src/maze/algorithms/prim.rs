@@ -0,0 +1,31 @@
+use super::{random_index, random_room, MazeAlgorithm};
+use crate::maze::{Disposition, Grid, MazeRng};
+
+/// Randomized Prim's algorithm: keeps a frontier of walls between the maze
+/// and the rooms outside it, and knocks down a random one each step.  Makes
+/// a maze that radiates out from its starting room, with many short dead
+/// ends.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Prim;
+
+impl MazeAlgorithm for Prim {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let mut visited = vec![false; grid.room_count()];
+        let start_room = random_room(grid, rng);
+        visited[start_room.index()] = true;
+
+        let mut frontier = grid.neighbors(start_room).collect::<Vec<_>>();
+        while !frontier.is_empty() {
+            let (room, wall) = frontier.swap_remove(random_index(rng, frontier.len()));
+            if visited[room.index()] {
+                continue;
+            }
+            grid.set_disposition(wall, Disposition::Absent);
+            visited[room.index()] = true;
+            frontier.extend(
+                grid.neighbors(room)
+                    .filter(|(neighbor, _)| !visited[neighbor.index()]),
+            );
+        }
+    }
+}
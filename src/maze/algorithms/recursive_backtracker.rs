@@ -0,0 +1,37 @@
+use rand::seq::IteratorRandom;
+
+use super::{random_room, MazeAlgorithm};
+use crate::maze::{Disposition, Grid, MazeRng};
+
+/// A randomized depth-first search: keeps walking into unvisited rooms, and
+/// backs up only when it hits a dead end.  Makes long, twisty corridors with
+/// few branches.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RecursiveBacktracker;
+
+impl MazeAlgorithm for RecursiveBacktracker {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let mut visited = vec![false; grid.room_count()];
+        let start_room = random_room(grid, rng);
+        visited[start_room.index()] = true;
+
+        // An explicit stack, since large mazes would overflow the real one.
+        let mut path = vec![start_room];
+        while let Some(&room) = path.last() {
+            match grid
+                .neighbors(room)
+                .filter(|(neighbor, _)| !visited[neighbor.index()])
+                .choose(rng)
+            {
+                Some((neighbor, wall)) => {
+                    grid.set_disposition(wall, Disposition::Absent);
+                    visited[neighbor.index()] = true;
+                    path.push(neighbor);
+                }
+                None => {
+                    path.pop();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use rand::Rng;
+
+use super::MazeAlgorithm;
+use crate::maze::{Disposition, Grid, MazeRng, MazeSpec, Topology};
+
+/// Recursive division: starts with no interior walls at all, then splits the
+/// grid in two with a wall that has a single gap in it, and repeats on each
+/// half.  Makes long straight walls and a boxy, structured look.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RecursiveDivision;
+
+/// A rectangle of rooms that hasn't been divided yet.
+struct Chamber {
+    x: i32,
+    z: i32,
+    width: i32,
+    depth: i32,
+}
+
+impl MazeAlgorithm for RecursiveDivision {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        for wall in grid.graph.edge_weights_mut() {
+            wall.disposition = Disposition::Absent;
+        }
+
+        let (x_min, x_max) = (*grid.x_range.start(), *grid.x_range.end());
+        let (z_min, z_max) = (*grid.z_range.start(), *grid.z_range.end());
+        let mut chambers = vec![Chamber {
+            x: x_min,
+            z: z_min,
+            width: x_max - x_min,
+            depth: z_max - z_min,
+        }];
+
+        while let Some(chamber) = chambers.pop() {
+            if chamber.width < 2 && chamber.depth < 2 {
+                continue;
+            }
+            // Split across the longer side, so chambers stay roughly square.
+            let split_along_x = if chamber.width == chamber.depth {
+                rng.gen_bool(0.5)
+            } else {
+                chamber.depth > chamber.width
+            };
+
+            if split_along_x {
+                // A wall parallel to the x axis, between rows `z - 1` and `z`.
+                let z = chamber.z + rng.gen_range(1..chamber.depth);
+                let gap = chamber.x + rng.gen_range(0..chamber.width);
+                for x in chamber.x..chamber.x + chamber.width {
+                    if x != gap {
+                        close(grid, (x, z - 1), (x, z));
+                    }
+                }
+                chambers.push(Chamber {
+                    depth: z - chamber.z,
+                    ..chamber
+                });
+                chambers.push(Chamber {
+                    z,
+                    depth: chamber.z + chamber.depth - z,
+                    ..chamber
+                });
+            } else {
+                // A wall parallel to the z axis, between columns `x - 1` and `x`.
+                let x = chamber.x + rng.gen_range(1..chamber.width);
+                let gap = chamber.z + rng.gen_range(0..chamber.depth);
+                for z in chamber.z..chamber.z + chamber.depth {
+                    if z != gap {
+                        close(grid, (x - 1, z), (x, z));
+                    }
+                }
+                chambers.push(Chamber {
+                    width: x - chamber.x,
+                    ..chamber
+                });
+                chambers.push(Chamber {
+                    x,
+                    width: chamber.x + chamber.width - x,
+                    ..chamber
+                });
+            }
+        }
+    }
+
+    /// Dividing walls only make sense along straight rows and columns of
+    /// rooms.  On a hex grid, rooms on either side of a row still touch
+    /// diagonally.  It also needs the whole rectangle, so no masks.
+    fn supports(&self, spec: &MazeSpec) -> bool {
+        spec.topology == Topology::Square && spec.mask.is_none()
+    }
+}
+
+fn close(grid: &mut Grid, a: (i32, i32), b: (i32, i32)) {
+    let a = grid.room_at(a.0, a.1).unwrap();
+    let b = grid.room_at(b.0, b.1).unwrap();
+    let wall = grid.graph.find_edge(a, b).unwrap();
+    grid.set_disposition(wall, Disposition::Present);
+}
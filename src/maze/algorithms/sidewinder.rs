@@ -0,0 +1,46 @@
+use rand::Rng;
+
+use super::MazeAlgorithm;
+use crate::maze::{Grid, MazeRng, MazeSpec, Topology};
+
+/// Sidewinder: works one row at a time, carving eastward runs and closing
+/// each run by opening north from a random room in it.  Leaves one unbroken
+/// corridor along the north edge.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Sidewinder;
+
+impl MazeAlgorithm for Sidewinder {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let (x_min, x_max) = (*grid.x_range.start(), *grid.x_range.end());
+        let (z_min, z_max) = (*grid.z_range.start(), *grid.z_range.end());
+
+        for z in z_min..z_max {
+            let mut run_start = x_min;
+            for x in x_min..x_max {
+                let room = grid.room_at(x, z).unwrap();
+                let at_east_edge = x + 1 == x_max;
+                let at_north_edge = z + 1 == z_max;
+                let close_run = at_east_edge || (!at_north_edge && rng.gen_bool(0.5));
+                if close_run {
+                    if !at_north_edge {
+                        let exit_x = rng.gen_range(run_start..=x);
+                        let exit = grid.room_at(exit_x, z).unwrap();
+                        let north = grid.room_at(exit_x, z + 1).unwrap();
+                        grid.open(exit, north);
+                    }
+                    run_start = x + 1;
+                } else {
+                    let east = grid.room_at(x + 1, z).unwrap();
+                    grid.open(room, east);
+                }
+            }
+        }
+    }
+
+    /// Polar rings don't line up in columns, and wrap around instead of
+    /// having an east edge to close the last run at.  Masks leave runs with
+    /// nowhere to carve north.
+    fn supports(&self, spec: &MazeSpec) -> bool {
+        spec.topology != Topology::Polar && spec.mask.is_none()
+    }
+}
@@ -0,0 +1,43 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use rand::seq::{IteratorRandom, SliceRandom};
+
+use super::{random_room, MazeAlgorithm};
+use crate::maze::{Disposition, Grid, MazeRng};
+
+/// Wilson's algorithm: repeatedly takes a loop-erased random walk from an
+/// unvisited room until it hits the maze, then carves that walk.  Like
+/// Aldous-Broder, every possible maze is equally likely, but it finishes much
+/// faster.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Wilson;
+
+impl MazeAlgorithm for Wilson {
+    fn carve(&self, grid: &mut Grid, rng: &mut MazeRng) {
+        let mut visited = vec![false; grid.room_count()];
+        let first_room = random_room(grid, rng);
+        visited[first_room.index()] = true;
+
+        let mut walk_starts = grid.graph.node_indices().collect::<Vec<_>>();
+        walk_starts.shuffle(rng);
+
+        // Remembering only the *last* way out of each room erases any loops
+        // the walk makes, since revisiting a room overwrites its exit.
+        let mut exits = vec![None::<(NodeIndex, EdgeIndex)>; grid.room_count()];
+        for walk_start in walk_starts {
+            let mut room = walk_start;
+            while !visited[room.index()] {
+                let exit = grid.neighbors(room).choose(rng).unwrap();
+                exits[room.index()] = Some(exit);
+                room = exit.0;
+            }
+
+            let mut room = walk_start;
+            while !visited[room.index()] {
+                let (next_room, wall) = exits[room.index()].unwrap();
+                grid.set_disposition(wall, Disposition::Absent);
+                visited[room.index()] = true;
+                room = next_room;
+            }
+        }
+    }
+}
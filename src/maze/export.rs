@@ -0,0 +1,442 @@
+//! Top-down pictures of mazes, for printing and posting.
+//!
+//! Both SVG and PNG come from the same list of shapes, worked out from the
+//! layout alone, so exporting needs neither a window nor a GPU.  Floors are
+//! drawn side by side, from the ground floor on the left, with north up.
+
+use std::{collections::HashMap, fmt::Write};
+
+use super::{solver, Disposition, MazeLayout, Room};
+
+#[derive(Copy, Clone, Debug)]
+pub struct ExportOptions {
+    /// How big each room comes out, in pixels.  (SVGs scale, of course, but
+    /// this is the size they ask to be shown at.)
+    pub pixels_per_room: f32,
+    /// Whether to draw the shortest path from the start to the goal.
+    pub show_solution: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            pixels_per_room: 24.0,
+            show_solution: false,
+        }
+    }
+}
+
+/// How thick walls are, in room units.  This matches the game's walls.
+const WALL_WIDTH: f32 = 0.1;
+/// The radius of the posts where walls meet.
+const POST_RADIUS: f32 = 0.075;
+const SOLUTION_WIDTH: f32 = 0.15;
+/// The space around each floor, in room units.
+const MARGIN: f32 = 0.5;
+
+/// The most pixels [`MazeLayout::to_png`] will draw, which is 8192×8192.
+/// The image is drawn in memory first, at three bytes a pixel.
+pub const MAX_PNG_PIXELS: usize = 8192 * 8192;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Color([u8; 3]);
+
+const BACKGROUND: Color = Color([0xff, 0xff, 0xff]);
+const WALL: Color = Color([0x20, 0x20, 0x20]);
+const START: Color = Color([0x9c, 0xd8, 0x9c]);
+const GOAL: Color = Color([0xf2, 0xc9, 0x4c]);
+const RAMP: Color = Color([0xb8, 0xcf, 0xe8]);
+const SOLUTION: Color = Color([0xd6, 0x3a, 0x3a]);
+
+impl Color {
+    fn hex(self) -> String {
+        let Color([r, g, b]) = self;
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// Something to draw, in room units with `y` pointing down (south).
+enum Shape {
+    Line {
+        points: Vec<(f32, f32)>,
+        width: f32,
+        color: Color,
+    },
+    Polygon {
+        points: Vec<(f32, f32)>,
+        color: Color,
+    },
+    Circle {
+        center: (f32, f32),
+        radius: f32,
+        color: Color,
+    },
+}
+
+/// A picture of a maze, with its top left corner at the origin.
+struct Drawing {
+    width: f32,
+    height: f32,
+    shapes: Vec<Shape>,
+}
+
+impl MazeLayout {
+    /// Draws the maze as an SVG document.
+    pub fn to_svg(&self, options: &ExportOptions) -> String {
+        let drawing = Drawing::new(self, options.show_solution);
+        let scale = options.pixels_per_room;
+        let points = |points: &[(f32, f32)]| {
+            points
+                .iter()
+                .map(|(x, y)| format!("{x:.3},{y:.3}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.3} {:.3}">"#,
+            drawing.width * scale,
+            drawing.height * scale,
+            drawing.width,
+            drawing.height,
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            BACKGROUND.hex()
+        )
+        .unwrap();
+        for shape in &drawing.shapes {
+            match shape {
+                Shape::Line {
+                    points: line,
+                    width,
+                    color,
+                } => writeln!(
+                    svg,
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{width}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                    points(line),
+                    color.hex(),
+                ),
+                Shape::Polygon {
+                    points: polygon,
+                    color,
+                } => writeln!(
+                    svg,
+                    r#"<polygon points="{}" fill="{}"/>"#,
+                    points(polygon),
+                    color.hex(),
+                ),
+                Shape::Circle {
+                    center: (x, y),
+                    radius,
+                    color,
+                } => writeln!(
+                    svg,
+                    r#"<circle cx="{x:.3}" cy="{y:.3}" r="{radius}" fill="{}"/>"#,
+                    color.hex(),
+                ),
+            }
+            .unwrap();
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Draws the maze as a PNG image.  `None` if it would take more than
+    /// [`MAX_PNG_PIXELS`]; fewer `pixels_per_room` make it fit.
+    pub fn to_png(&self, options: &ExportOptions) -> Option<Vec<u8>> {
+        let drawing = Drawing::new(self, options.show_solution);
+        let scale = options.pixels_per_room;
+        let (width, height) = (
+            (drawing.width * scale).ceil() as usize,
+            (drawing.height * scale).ceil() as usize,
+        );
+        if width.checked_mul(height)? > MAX_PNG_PIXELS {
+            return None;
+        }
+        let mut canvas = Canvas::new(width, height);
+        let to_pixels = |(x, y): (f32, f32)| (x * scale, y * scale);
+        for shape in &drawing.shapes {
+            match shape {
+                Shape::Line {
+                    points,
+                    width,
+                    color,
+                } => {
+                    for segment in points.windows(2) {
+                        canvas.stroke(
+                            to_pixels(segment[0]),
+                            to_pixels(segment[1]),
+                            width * scale,
+                            *color,
+                        );
+                    }
+                }
+                Shape::Polygon { points, color } => {
+                    let points = points.iter().copied().map(to_pixels).collect::<Vec<_>>();
+                    canvas.fill_polygon(&points, *color);
+                }
+                Shape::Circle {
+                    center,
+                    radius,
+                    color,
+                } => canvas.fill_circle(to_pixels(*center), radius * scale, *color),
+            }
+        }
+        Some(canvas.encode())
+    }
+}
+
+impl Drawing {
+    fn new(layout: &MazeLayout, show_solution: bool) -> Self {
+        let topology = layout.spec.topology;
+        let mut shapes = Vec::new();
+
+        // Where each floor goes: its offset from the maze's own coordinates
+        // (with `z` flipped to point down), and how much space it takes.
+        let mut offsets = Vec::new();
+        let (mut width, mut height) = (0.0f32, 0.0f32);
+        for floor in layout.floors() {
+            let (mut min, mut max) = (
+                (f32::INFINITY, f32::INFINITY),
+                (f32::NEG_INFINITY, f32::NEG_INFINITY),
+            );
+            for wall in layout
+                .walls()
+                .filter(|wall| wall.room.floor == floor && !wall.side.is_vertical())
+            {
+                for (x, z) in topology.wall_points(&wall) {
+                    min = (min.0.min(x), min.1.min(-z));
+                    max = (max.0.max(x), max.1.max(-z));
+                }
+            }
+            if min.0 > max.0 {
+                // A floor without any walls has nothing to draw.
+                (min, max) = ((0.0, 0.0), (0.0, 0.0));
+            }
+            offsets.push((width + MARGIN - min.0, MARGIN - min.1));
+            width += max.0 - min.0 + MARGIN * 2.0;
+            height = height.max(max.1 - min.1 + MARGIN * 2.0);
+        }
+        let place = |floor: i32, (x, z): (f32, f32)| {
+            let (dx, dy) = offsets[floor as usize];
+            (x + dx, -z + dy)
+        };
+        let fill_room = |shapes: &mut Vec<Shape>, room: Room, color: Color| {
+            shapes.push(Shape::Polygon {
+                points: topology
+                    .room_outline(room)
+                    .into_iter()
+                    .map(|point| place(room.floor, point))
+                    .collect(),
+                color,
+            });
+        };
+
+        for ramp in layout.ramps() {
+            fill_room(&mut shapes, ramp.lower, RAMP);
+            fill_room(&mut shapes, ramp.upper, RAMP);
+        }
+        fill_room(&mut shapes, layout.start, START);
+        fill_room(&mut shapes, layout.goal, GOAL);
+
+        if show_solution {
+            // Split the path wherever it changes floors.
+            let mut line = Vec::new();
+            let ids_by_room = layout
+                .graph
+                .node_indices()
+                .map(|id| (layout.graph[id], id))
+                .collect::<HashMap<_, _>>();
+            let path = solver::breadth_first(layout, layout.start, layout.goal);
+            let mut rooms = path.unwrap_or_default().into_iter().peekable();
+            while let Some(room) = rooms.next() {
+                line.push(place(room.floor, topology.room_center(room)));
+                let wall = rooms
+                    .peek()
+                    .and_then(|next| {
+                        layout
+                            .graph
+                            .find_edge(ids_by_room[&room], ids_by_room[next])
+                    })
+                    .map(|wall| layout.graph[wall]);
+                match wall {
+                    Some(wall) if !wall.side.is_vertical() => {
+                        line.push(place(room.floor, topology.wall_midpoint(&wall)));
+                    }
+                    _ => shapes.push(Shape::Line {
+                        points: std::mem::take(&mut line),
+                        width: SOLUTION_WIDTH,
+                        color: SOLUTION,
+                    }),
+                }
+            }
+        }
+
+        for wall in layout.walls() {
+            if wall.disposition == Disposition::Absent || wall.side.is_vertical() {
+                continue;
+            }
+            shapes.push(Shape::Line {
+                points: topology
+                    .wall_points(&wall)
+                    .into_iter()
+                    .map(|point| place(wall.room.floor, point))
+                    .collect(),
+                width: WALL_WIDTH,
+                color: WALL,
+            });
+        }
+        for floor in layout.floors() {
+            for corner in layout.corners(floor) {
+                shapes.push(Shape::Circle {
+                    center: place(floor, corner),
+                    radius: POST_RADIUS,
+                    color: WALL,
+                });
+            }
+        }
+
+        Drawing {
+            width,
+            height,
+            shapes,
+        }
+    }
+}
+
+/// An RGB image to draw into, with simple antialiasing.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        let Color(background) = BACKGROUND;
+        Canvas {
+            width,
+            height,
+            pixels: background.repeat(width * height),
+        }
+    }
+
+    /// Mixes `color` into the pixel at `(x, y)`, where `coverage` is how much
+    /// of the pixel the shape covers.
+    fn blend(&mut self, x: usize, y: usize, color: Color, coverage: f32) {
+        let index = (y * self.width + x) * 3;
+        for (pixel, channel) in self.pixels[index..index + 3].iter_mut().zip(color.0) {
+            *pixel = (*pixel as f32 + (channel as f32 - *pixel as f32) * coverage).round() as u8;
+        }
+    }
+
+    /// Calls `coverage` with the center of every pixel in the box from `min`
+    /// to `max`, and blends in as much of `color` as it says.
+    fn paint(
+        &mut self,
+        min: (f32, f32),
+        max: (f32, f32),
+        color: Color,
+        coverage: impl Fn((f32, f32)) -> f32,
+    ) {
+        let clamp = |value: f32, limit: usize| (value.max(0.0) as usize).min(limit);
+        for y in clamp(min.1.floor(), self.height)..clamp(max.1.ceil(), self.height) {
+            for x in clamp(min.0.floor(), self.width)..clamp(max.0.ceil(), self.width) {
+                let coverage = coverage((x as f32 + 0.5, y as f32 + 0.5)).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Draws a straight line with round ends.
+    fn stroke(&mut self, a: (f32, f32), b: (f32, f32), width: f32, color: Color) {
+        let reach = width / 2.0 + 1.0;
+        self.paint(
+            (a.0.min(b.0) - reach, a.1.min(b.1) - reach),
+            (a.0.max(b.0) + reach, a.1.max(b.1) + reach),
+            color,
+            |point| width / 2.0 + 0.5 - distance_to_segment(point, a, b),
+        );
+    }
+
+    fn fill_circle(&mut self, center: (f32, f32), radius: f32, color: Color) {
+        let reach = radius + 1.0;
+        self.paint(
+            (center.0 - reach, center.1 - reach),
+            (center.0 + reach, center.1 + reach),
+            color,
+            |(x, y)| radius + 0.5 - (x - center.0).hypot(y - center.1),
+        );
+    }
+
+    fn fill_polygon(&mut self, points: &[(f32, f32)], color: Color) {
+        let edges = || points.iter().zip(points.iter().cycle().skip(1));
+        let min = points
+            .iter()
+            .fold((f32::INFINITY, f32::INFINITY), |min, p| {
+                (min.0.min(p.0), min.1.min(p.1))
+            });
+        let max = points
+            .iter()
+            .fold((f32::NEG_INFINITY, f32::NEG_INFINITY), |max, p| {
+                (max.0.max(p.0), max.1.max(p.1))
+            });
+        self.paint(
+            (min.0 - 1.0, min.1 - 1.0),
+            (max.0 + 1.0, max.1 + 1.0),
+            color,
+            |point| {
+                let (x, y) = point;
+                // Even-odd rule: count the edges crossed by a ray going east.
+                let inside = edges()
+                    .filter(|((x0, y0), (x1, y1))| {
+                        (*y0 > y) != (*y1 > y) && x < x0 + (y - y0) / (y1 - y0) * (x1 - x0)
+                    })
+                    .count()
+                    % 2
+                    == 1;
+                let edge_distance = edges()
+                    .map(|(a, b)| distance_to_segment(point, *a, *b))
+                    .fold(f32::INFINITY, f32::min);
+                if inside {
+                    0.5 + edge_distance
+                } else {
+                    0.5 - edge_distance
+                }
+            },
+        );
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .expect("writing to memory shouldn't fail");
+        writer
+            .write_image_data(&self.pixels)
+            .expect("writing to memory shouldn't fail");
+        writer.finish().expect("writing to memory shouldn't fail");
+        bytes
+    }
+}
+
+fn distance_to_segment((x, y): (f32, f32), (x0, y0): (f32, f32), (x1, y1): (f32, f32)) -> f32 {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((x - x0) * dx + (y - y0) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (x - (x0 + t * dx)).hypot(y - (y0 + t * dy))
+}
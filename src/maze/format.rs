@@ -0,0 +1,544 @@
+//! Saving mazes to files and loading them back.
+//!
+//! A [`MazeLayout`] round-trips through JSON and RON exactly: the same rooms
+//! in the same order, every wall with its disposition, the start and goal,
+//! and the spec it was generated from (or that it was drawn by hand).  So a
+//! loaded maze spawns just like the original did, even on a build whose
+//! generator would now carve it differently.  Loading checks that the spec
+//! could generate a maze again, since resetting a loaded maze does.
+//!
+//! Square mazes can also be drawn as plain text, for humans to read.  That
+//! drawing is one-way; it leaves out too much to load from.
+
+use std::{collections::HashMap, error::Error, fmt, ops::RangeInclusive};
+
+use petgraph::{graph::NodeIndex, Graph};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Algorithm, Disposition, MazeAlgorithm, MazeLayout, MazeSpec, Room, Side, Topology, Wall,
+};
+
+/// How a [`MazeLayout`] looks on disk.  The graph becomes a list of rooms
+/// and a list of the walls between them, in graph order; each wall's rooms
+/// follow from its room and side.
+#[derive(Serialize, Deserialize)]
+struct SavedLayout {
+    spec: MazeSpec,
+    /// Files from before levels were marked leave this out, and load as
+    /// generated mazes.
+    #[serde(default)]
+    hand_drawn: bool,
+    start: Room,
+    goal: Room,
+    rooms: Vec<Room>,
+    walls: Vec<Wall>,
+    border: Vec<Wall>,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Json(serde_json::Error),
+    Ron(ron::error::SpannedError),
+    /// The file parsed, but doesn't describe a maze.
+    Invalid(InvalidLayout),
+}
+
+/// What can be wrong with a maze file that parses fine.
+#[derive(Debug)]
+pub enum InvalidLayout {
+    /// The spec's algorithm can't generate mazes like the spec's, so
+    /// resetting the maze couldn't generate it again.
+    UnsupportedAlgorithm(Algorithm),
+    /// The spec's `braid` isn't a fraction from 0.0 to 1.0.
+    BraidOutOfRange(f32),
+    /// The spec's grid has no rows or no columns.
+    EmptyRange,
+    /// The spec asks for a floor with no rooms on it.
+    EmptyFloor(i32),
+    /// The same room is listed twice.
+    DuplicateRoom(Room),
+    /// A wall, the start or the goal refers to a room that isn't listed.
+    MissingRoom(Room),
+    /// A room is above or below the floors the spec asks for.
+    FloorOutOfRange(Room),
+    /// A wall is on a side its room doesn't have, like a square room's
+    /// north-east.
+    InvalidSide(Wall),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Json(err) => write!(f, "couldn't parse the maze's JSON: {err}"),
+            LoadError::Ron(err) => write!(f, "couldn't parse the maze's RON: {err}"),
+            LoadError::Invalid(InvalidLayout::UnsupportedAlgorithm(algorithm)) => {
+                write!(f, "{} can't generate the maze's shape", algorithm.name())
+            }
+            LoadError::Invalid(InvalidLayout::BraidOutOfRange(braid)) => {
+                write!(f, "braid {braid} isn't from 0.0 to 1.0")
+            }
+            LoadError::Invalid(InvalidLayout::EmptyRange) => {
+                write!(f, "the maze's grid has no rows or no columns")
+            }
+            LoadError::Invalid(InvalidLayout::EmptyFloor(floor)) => {
+                write!(f, "floor {floor} has no rooms")
+            }
+            LoadError::Invalid(InvalidLayout::DuplicateRoom(room)) => {
+                write!(f, "{room:?} is listed twice")
+            }
+            LoadError::Invalid(InvalidLayout::MissingRoom(room)) => {
+                write!(f, "{room:?} isn't one of the maze's rooms")
+            }
+            LoadError::Invalid(InvalidLayout::FloorOutOfRange(room)) => {
+                write!(f, "{room:?} isn't on one of the maze's floors")
+            }
+            LoadError::Invalid(InvalidLayout::InvalidSide(wall)) => {
+                write!(f, "{:?} has no {:?} side", wall.room, wall.side)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Json(err) => Some(err),
+            LoadError::Ron(err) => Some(err),
+            LoadError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<InvalidLayout> for LoadError {
+    fn from(err: InvalidLayout) -> Self {
+        LoadError::Invalid(err)
+    }
+}
+
+impl SavedLayout {
+    fn new(layout: &MazeLayout) -> Self {
+        SavedLayout {
+            spec: layout.spec.clone(),
+            hand_drawn: layout.hand_drawn,
+            start: layout.start,
+            goal: layout.goal,
+            rooms: layout.graph.node_weights().copied().collect(),
+            walls: layout.graph.edge_weights().copied().collect(),
+            border: layout.border.clone(),
+        }
+    }
+
+    /// Rebuilds the graph, checking that everything adds up along the way.
+    fn into_layout(self) -> Result<MazeLayout, InvalidLayout> {
+        check_spec(&self.spec)?;
+        let topology = self.spec.topology;
+        let floors = 0..self.spec.floors.max(1);
+        let mut graph = Graph::new_undirected();
+        let mut ids_by_room = HashMap::<Room, NodeIndex>::new();
+        for room in self.rooms {
+            if !floors.contains(&room.floor) {
+                return Err(InvalidLayout::FloorOutOfRange(room));
+            }
+            if ids_by_room.insert(room, graph.add_node(room)).is_some() {
+                return Err(InvalidLayout::DuplicateRoom(room));
+            }
+        }
+        let id = |room: Room| {
+            ids_by_room
+                .get(&room)
+                .copied()
+                .ok_or(InvalidLayout::MissingRoom(room))
+        };
+        // Looking up a neighbor on a side the room doesn't have panics.
+        let check_side = |wall: &Wall| {
+            if wall.side.is_vertical() || topology.sides(wall.room).contains(&wall.side) {
+                Ok(())
+            } else {
+                Err(InvalidLayout::InvalidSide(*wall))
+            }
+        };
+        for wall in self.walls {
+            check_side(&wall)?;
+            let neighbor = topology.neighbor(wall.room, wall.side);
+            graph.add_edge(id(wall.room)?, id(neighbor)?, wall);
+        }
+        for wall in &self.border {
+            check_side(wall)?;
+            // See `Wall::room` for border walls that belong outside the maze.
+            id(wall.room).or_else(|_| id(topology.neighbor(wall.room, wall.side)))?;
+        }
+        id(self.start)?;
+        id(self.goal)?;
+        // Everything that walks the floors in turn walks the empty ones too.
+        let empty_floor = floors
+            .clone()
+            .find(|floor| graph.node_weights().all(|room| room.floor != *floor));
+        if let Some(floor) = empty_floor {
+            return Err(InvalidLayout::EmptyFloor(floor));
+        }
+
+        Ok(MazeLayout {
+            spec: self.spec,
+            graph,
+            border: self.border,
+            start: self.start,
+            goal: self.goal,
+            hand_drawn: self.hand_drawn,
+        })
+    }
+}
+
+/// Checks that resetting to `spec` could generate a maze from it.
+fn check_spec(spec: &MazeSpec) -> Result<(), InvalidLayout> {
+    if !spec.algorithm.supports(spec) {
+        return Err(InvalidLayout::UnsupportedAlgorithm(spec.algorithm));
+    }
+    // This also catches NaN.
+    if !(0.0..=1.0).contains(&spec.braid) {
+        return Err(InvalidLayout::BraidOutOfRange(spec.braid));
+    }
+    // Rooms fill `start..end`, and polar mazes ignore the columns.
+    let empty = |range: &RangeInclusive<i32>| range.start() >= range.end();
+    if empty(&spec.z_range) || (spec.topology != Topology::Polar && empty(&spec.x_range)) {
+        return Err(InvalidLayout::EmptyRange);
+    }
+    Ok(())
+}
+
+impl MazeLayout {
+    /// The maze as compact JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&SavedLayout::new(self)).expect("mazes should serialize")
+    }
+
+    /// The maze as RON, one room or wall per line.
+    pub fn to_ron(&self) -> String {
+        let config = ron::ser::PrettyConfig::new().depth_limit(2);
+        ron::ser::to_string_pretty(&SavedLayout::new(self), config).expect("mazes should serialize")
+    }
+
+    /// Loads a maze saved by [`MazeLayout::to_json`].
+    pub fn from_json(text: &str) -> Result<Self, LoadError> {
+        let saved = serde_json::from_str::<SavedLayout>(text).map_err(LoadError::Json)?;
+        Ok(saved.into_layout()?)
+    }
+
+    /// Loads a maze saved by [`MazeLayout::to_ron`].
+    pub fn from_ron(text: &str) -> Result<Self, LoadError> {
+        let saved = ron::from_str::<SavedLayout>(text).map_err(LoadError::Ron)?;
+        Ok(saved.into_layout()?)
+    }
+
+    /// Loads a maze from JSON or RON, whichever `text` turns out to be.
+    pub fn parse(text: &str) -> Result<Self, LoadError> {
+        if text.trim_start().starts_with('{') {
+            Self::from_json(text)
+        } else {
+            Self::from_ron(text)
+        }
+    }
+
+    /// Draws the maze in text, north up, one floor after another from the
+    /// ground up.  Each room is three characters wide, with `S` at the start,
+    /// `G` at the goal, and `^` or `v` where a ramp leads up or down.  Only
+    /// square mazes can be drawn this way.
+    pub fn to_ascii(&self) -> Option<String> {
+        if self.spec.topology != Topology::Square {
+            return None;
+        }
+        let mut ids_by_room = HashMap::<Room, NodeIndex>::new();
+        for id in self.graph.node_indices() {
+            ids_by_room.insert(self.graph[id], id);
+        }
+        let mut ramps = HashMap::<Room, char>::new();
+        for ramp in self.ramps() {
+            ramps.insert(ramp.lower, '^');
+            ramps.insert(ramp.upper, 'v');
+        }
+
+        let floors = self
+            .floors()
+            .filter_map(|floor| self.floor_to_ascii(floor, &ids_by_room, &ramps))
+            .collect::<Vec<_>>();
+        Some(floors.join("\n"))
+    }
+
+    fn floor_to_ascii(
+        &self,
+        floor: i32,
+        ids_by_room: &HashMap<Room, NodeIndex>,
+        ramps: &HashMap<Room, char>,
+    ) -> Option<String> {
+        let rooms = ids_by_room
+            .keys()
+            .filter(|room| room.floor == floor)
+            .collect::<Vec<_>>();
+        let column_min = rooms.iter().map(|room| room.column).min()?;
+        let column_max = rooms.iter().map(|room| room.column).max()?;
+        let row_min = rooms.iter().map(|room| room.row).min()?;
+        let row_max = rooms.iter().map(|room| room.row).max()?;
+        let columns = (column_max - column_min + 1) as usize;
+        let rows = (row_max - row_min + 1) as usize;
+
+        // Cell `(c, r)` is the room `c` columns from the west and `r` rows
+        // from the north.  Its inside is three characters on text line
+        // `2r + 1`, starting at character `4c + 1`, with its north wall
+        // above and its west wall to the left.
+        let room = |c: usize, r: usize| Room {
+            column: column_min + c as i32,
+            row: row_max - r as i32,
+            floor,
+        };
+        let present = |room: Room| ids_by_room.contains_key(&room);
+        // A wall stands between two cells unless there's a passage or
+        // neither is part of the maze.
+        let wall_between = |a: Room, b: Room| match (ids_by_room.get(&a), ids_by_room.get(&b)) {
+            (Some(&a), Some(&b)) => self
+                .graph
+                .find_edge(a, b)
+                .filter(|wall| self.graph[*wall].disposition == Disposition::Absent)
+                .is_none(),
+            (None, None) => false,
+            _ => true,
+        };
+
+        let mut lines = vec![vec![' '; columns * 4 + 1]; rows * 2 + 1];
+        for r in 0..=rows {
+            for c in 0..=columns {
+                let here = room(c, r);
+                if c < columns && wall_between(here, Topology::Square.neighbor(here, Side::North)) {
+                    lines[r * 2][c * 4 + 1..c * 4 + 4].fill('-');
+                }
+                if r < rows && wall_between(here, Topology::Square.neighbor(here, Side::West)) {
+                    lines[r * 2 + 1][c * 4] = '|';
+                }
+                if c < columns && r < rows && present(here) {
+                    lines[r * 2 + 1][c * 4 + 2] = if here == self.start {
+                        'S'
+                    } else if here == self.goal {
+                        'G'
+                    } else {
+                        ramps.get(&here).copied().unwrap_or(' ')
+                    };
+                }
+            }
+        }
+        // Corners go wherever walls meet.
+        for r in 0..=rows {
+            for c in 0..=columns {
+                let (line, column) = (r * 2, c * 4);
+                let touches = [
+                    column > 0 && lines[line][column - 1] == '-',
+                    lines[line].get(column + 1) == Some(&'-'),
+                    line > 0 && lines[line - 1][column] == '|',
+                    lines.get(line + 1).map(|next| next[column]) == Some('|'),
+                ];
+                if touches.contains(&true) {
+                    lines[line][column] = '+';
+                }
+            }
+        }
+
+        Some(
+            lines
+                .into_iter()
+                .map(|line| String::from_iter(line).trim_end().to_owned() + "\n")
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{Algorithm, Level};
+
+    fn layout(topology: Topology) -> MazeLayout {
+        MazeLayout::generate(&MazeSpec {
+            topology,
+            x_range: 0..=3,
+            z_range: 0..=3,
+            mask: None,
+            floors: 2,
+            algorithm: Algorithm::default(),
+            braid: 0.0,
+            seed: 7,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn walls_on_sides_rooms_dont_have_are_invalid() {
+        let mut saved = serde_json::to_value(SavedLayout::new(&layout(Topology::Square))).unwrap();
+        saved["walls"][0]["side"] = "NorthEast".into();
+        assert!(matches!(
+            MazeLayout::from_json(&saved.to_string()),
+            Err(LoadError::Invalid(InvalidLayout::InvalidSide(Wall {
+                side: Side::NorthEast,
+                ..
+            })))
+        ));
+
+        let mut saved = serde_json::to_value(SavedLayout::new(&layout(Topology::Polar))).unwrap();
+        saved["border"][0]["side"] = "West".into();
+        assert!(matches!(
+            MazeLayout::from_json(&saved.to_string()),
+            Err(LoadError::Invalid(InvalidLayout::InvalidSide(_)))
+        ));
+    }
+
+    /// Whether two mazes are the same, down to the order of their rooms and
+    /// walls.
+    fn same(a: &MazeLayout, b: &MazeLayout) -> bool {
+        a.spec == b.spec
+            && a.start == b.start
+            && a.goal == b.goal
+            && a.border == b.border
+            && a.graph.node_weights().eq(b.graph.node_weights())
+            && a.graph.edge_weights().eq(b.graph.edge_weights())
+    }
+
+    #[test]
+    fn json_round_trips() {
+        for topology in Topology::ALL {
+            let layout = layout(topology);
+            let loaded = MazeLayout::from_json(&layout.to_json()).unwrap();
+            assert!(same(&loaded, &layout), "{topology:?}");
+            assert!(same(
+                &MazeLayout::parse(&layout.to_json()).unwrap(),
+                &layout
+            ));
+        }
+    }
+
+    #[test]
+    fn ron_round_trips() {
+        for topology in Topology::ALL {
+            let layout = layout(topology);
+            let loaded = MazeLayout::from_ron(&layout.to_ron()).unwrap();
+            assert!(same(&loaded, &layout), "{topology:?}");
+            assert!(same(&MazeLayout::parse(&layout.to_ron()).unwrap(), &layout));
+        }
+    }
+
+    #[test]
+    fn broken_files_say_why() {
+        assert!(matches!(
+            MazeLayout::parse("{\"spec\": 3}"),
+            Err(LoadError::Json(_))
+        ));
+        assert!(matches!(
+            MazeLayout::parse("(spec: 3)"),
+            Err(LoadError::Ron(_))
+        ));
+
+        let saved = serde_json::to_value(SavedLayout::new(&layout(Topology::Square))).unwrap();
+        let load = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut saved = saved.clone();
+            edit(&mut saved);
+            MazeLayout::from_json(&saved.to_string())
+        };
+        assert!(matches!(
+            load(&|saved| {
+                let room = saved["rooms"][0].clone();
+                saved["rooms"].as_array_mut().unwrap().push(room);
+            }),
+            Err(LoadError::Invalid(InvalidLayout::DuplicateRoom(_)))
+        ));
+        assert!(matches!(
+            load(&|saved| saved["goal"]["column"] = 100.into()),
+            Err(LoadError::Invalid(InvalidLayout::MissingRoom(Room {
+                column: 100,
+                ..
+            })))
+        ));
+        assert!(matches!(
+            load(&|saved| saved["rooms"][0]["floor"] = 2.into()),
+            Err(LoadError::Invalid(InvalidLayout::FloorOutOfRange(Room {
+                floor: 2,
+                ..
+            })))
+        ));
+        // A selection policy has to have some weight to pick rooms with.
+        assert!(matches!(
+            load(&|saved| {
+                saved["spec"]["algorithm"] = serde_json::json!({
+                    "GrowingTree": {"newest": 0, "oldest": 0, "middle": 0, "random": 0}
+                })
+            }),
+            Err(LoadError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn specs_that_cant_be_generated_again_are_invalid() {
+        let load = |topology: Topology, edit: &dyn Fn(&mut MazeSpec)| {
+            let mut saved = SavedLayout::new(&layout(topology));
+            edit(&mut saved.spec);
+            // RON, unlike JSON, can write NaN.
+            MazeLayout::from_ron(&ron::to_string(&saved).unwrap())
+        };
+        assert!(load(Topology::Square, &|spec| spec.algorithm = Algorithm::Eller).is_ok());
+        assert!(matches!(
+            load(Topology::Polar, &|spec| spec.algorithm = Algorithm::Eller),
+            Err(LoadError::Invalid(InvalidLayout::UnsupportedAlgorithm(
+                Algorithm::Eller
+            )))
+        ));
+        for braid in [f32::NAN, -0.5, 1.5] {
+            assert!(matches!(
+                load(Topology::Square, &|spec| spec.braid = braid),
+                Err(LoadError::Invalid(InvalidLayout::BraidOutOfRange(_)))
+            ));
+        }
+        assert!(matches!(
+            load(Topology::Square, &|spec| spec.x_range = 3..=3),
+            Err(LoadError::Invalid(InvalidLayout::EmptyRange))
+        ));
+        assert!(matches!(
+            load(Topology::Polar, &|spec| spec.z_range = 0..=0),
+            Err(LoadError::Invalid(InvalidLayout::EmptyRange))
+        ));
+        assert!(matches!(
+            load(Topology::Square, &|spec| spec.floors = i32::MAX),
+            Err(LoadError::Invalid(InvalidLayout::EmptyFloor(2)))
+        ));
+    }
+
+    #[test]
+    fn levels_stay_hand_drawn() {
+        let level = Level::parse("#####\n#S G#\n#####").unwrap();
+        for text in [level.layout.to_json(), level.layout.to_ron()] {
+            assert!(MazeLayout::parse(&text).unwrap().hand_drawn);
+        }
+        assert!(!layout(Topology::Square).hand_drawn);
+    }
+
+    #[test]
+    fn square_mazes_draw_as_text() {
+        let corridor = MazeLayout::generate(&MazeSpec {
+            topology: Topology::Square,
+            x_range: 0..=3,
+            z_range: 0..=1,
+            mask: None,
+            floors: 1,
+            algorithm: Algorithm::RecursiveBacktracker,
+            braid: 0.0,
+            seed: 7,
+        })
+        .unwrap();
+        assert_eq!(
+            corridor.to_ascii().unwrap(),
+            "+---+---+---+\n\
+             | G       S |\n\
+             +---+---+---+\n",
+        );
+
+        assert_eq!(layout(Topology::Hex).to_ascii(), None);
+        // One drawing per floor.
+        let square = layout(Topology::Square).to_ascii().unwrap();
+        assert_eq!(square.matches('S').count() + square.matches('G').count(), 2);
+        assert_eq!(square.split("\n\n").count(), 2);
+    }
+}
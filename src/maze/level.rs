@@ -0,0 +1,399 @@
+//! Hand-drawn levels, for when a maze shouldn't be random.
+//!
+//! A level is a square maze drawn in text, north up:
+//!
+//! ```text
+//! #########
+//! #S    # #
+//! ##### # #
+//! #   #   #
+//! # # ### #
+//! # #    G#
+//! #########
+//! ```
+//!
+//! Rooms sit at odd columns of odd lines (counting from zero), with the
+//! characters between them standing for the walls.  `#` is a wall, and a
+//! space or `.` is a passage.  A `#` where a room would be leaves that room
+//! out, so levels can have any shape.  `S` and `G` mark the start and the
+//! goal rooms.  What's drawn at the corners between walls doesn't matter, as
+//! long as it's one of those characters.  Short lines are padded with `#`.
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use petgraph::{unionfind::UnionFind, visit::EdgeRef};
+
+use super::{Algorithm, Disposition, Grid, MazeLayout, MazeSpec, Room, Side, Topology};
+
+/// A maze parsed from text, and anything odd about it.
+#[derive(Clone, Debug)]
+pub struct Level {
+    /// The level's maze.  Its spec describes the grid it's drawn on, but
+    /// nothing was generated, so the spec's algorithm and seed mean nothing;
+    /// the layout is marked `hand_drawn` to say so.
+    pub layout: MazeLayout,
+    pub warnings: Vec<LevelWarning>,
+}
+
+/// Why a level couldn't be parsed.  Lines and columns count from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelError {
+    /// A character that levels aren't drawn with.
+    UnexpectedCharacter {
+        line: usize,
+        column: usize,
+        character: char,
+    },
+    /// An `S` or `G` where a wall or a corner goes, rather than a room.
+    MarkerOutsideRoom {
+        line: usize,
+        column: usize,
+        marker: char,
+    },
+    /// A second `S` or `G`.
+    DuplicateMarker {
+        line: usize,
+        column: usize,
+        marker: char,
+    },
+    /// No `S` or no `G` at all.
+    MissingMarker(char),
+}
+
+/// Something that makes a level less than playable, though it parses.
+/// Lines and columns count from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelWarning {
+    /// There's no way from the start to the goal.
+    UnreachableGoal,
+    /// A group of rooms that can't be reached from the start.  The line and
+    /// column are those of its first room.
+    DisconnectedRegion {
+        line: usize,
+        column: usize,
+        rooms: usize,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::UnexpectedCharacter {
+                line,
+                column,
+                character,
+            } => write!(
+                f,
+                "line {line}, column {column}: levels are drawn with `#`, ` `, `.`, `S` and `G`, not {character:?}"
+            ),
+            LevelError::MarkerOutsideRoom {
+                line,
+                column,
+                marker,
+            } => write!(
+                f,
+                "line {line}, column {column}: `{marker}` has to go where a room is, at an odd column of an odd line (counting from zero)"
+            ),
+            LevelError::DuplicateMarker {
+                line,
+                column,
+                marker,
+            } => write!(f, "line {line}, column {column}: there's already a `{marker}`"),
+            LevelError::MissingMarker(marker) => write!(f, "the level has no `{marker}`"),
+        }
+    }
+}
+
+impl Error for LevelError {}
+
+impl fmt::Display for LevelWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelWarning::UnreachableGoal => write!(f, "the goal can't be reached from the start"),
+            LevelWarning::DisconnectedRegion {
+                line,
+                column,
+                rooms,
+            } => write!(
+                f,
+                "line {line}, column {column}: {rooms} room(s) here can't be reached from the start"
+            ),
+        }
+    }
+}
+
+impl Level {
+    pub fn parse(text: &str) -> Result<Self, LevelError> {
+        let mut lines = text
+            .lines()
+            .map(|line| line.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        while matches!(lines.last(), Some(line) if line.is_empty()) {
+            lines.pop();
+        }
+        let at = |line: usize, column: usize| {
+            lines
+                .get(line)
+                .and_then(|line| line.get(column))
+                .copied()
+                .unwrap_or('#')
+        };
+
+        // Check every character, and find the start and goal on the way.
+        let mut start = None;
+        let mut goal = None;
+        for (line, characters) in lines.iter().enumerate() {
+            for (column, &character) in characters.iter().enumerate() {
+                let (line_number, column_number) = (line + 1, column + 1);
+                match character {
+                    '#' | ' ' | '.' => {}
+                    'S' | 'G' => {
+                        if line % 2 == 0 || column % 2 == 0 {
+                            return Err(LevelError::MarkerOutsideRoom {
+                                line: line_number,
+                                column: column_number,
+                                marker: character,
+                            });
+                        }
+                        let marker = if character == 'S' {
+                            &mut start
+                        } else {
+                            &mut goal
+                        };
+                        if marker.is_some() {
+                            return Err(LevelError::DuplicateMarker {
+                                line: line_number,
+                                column: column_number,
+                                marker: character,
+                            });
+                        }
+                        *marker = Some((line, column));
+                    }
+                    _ => {
+                        return Err(LevelError::UnexpectedCharacter {
+                            line: line_number,
+                            column: column_number,
+                            character,
+                        })
+                    }
+                }
+            }
+        }
+        let start = start.ok_or(LevelError::MissingMarker('S'))?;
+        let goal = goal.ok_or(LevelError::MissingMarker('G'))?;
+
+        let columns = lines.iter().map(Vec::len).max().unwrap_or(0) / 2;
+        let rows = lines.len() / 2;
+        // Where each room is drawn, as zero-based line and column.
+        let position = |room: Room| {
+            (
+                (rows as i32 - 1 - room.row) as usize * 2 + 1,
+                room.column as usize * 2 + 1,
+            )
+        };
+        let room_at = |(line, column): (usize, usize)| Room {
+            column: (column / 2) as i32,
+            row: (rows - 1 - line / 2) as i32,
+            floor: 0,
+        };
+
+        // Lay the rooms out the way `Grid::new` would, so the graph comes out
+        // just like a generated one.
+        let mut rooms = Vec::new();
+        for column in 0..columns as i32 {
+            for row in 0..rows as i32 {
+                let room = Room {
+                    column,
+                    row,
+                    floor: 0,
+                };
+                let (line, column) = position(room);
+                if at(line, column) != '#' {
+                    rooms.push(room);
+                }
+            }
+        }
+        let mut grid =
+            Grid::with_rooms(Topology::Square, 0..=columns as i32, 0..=rows as i32, rooms);
+        for wall in grid.graph.edge_weights_mut() {
+            let (line, column) = position(wall.room);
+            let (line, column) = match wall.side {
+                Side::South => (line + 1, column),
+                Side::West => (line, column - 1),
+                side => unreachable!("square rooms don't own their {side:?} walls"),
+            };
+            wall.disposition = if at(line, column) == '#' {
+                Disposition::Present
+            } else {
+                Disposition::Absent
+            };
+        }
+
+        // Group rooms by which ones can reach each other, and warn about
+        // every group besides the start's.
+        let mut regions = UnionFind::<usize>::new(grid.room_count());
+        for edge in grid.graph.edge_references() {
+            if edge.weight().disposition == Disposition::Absent {
+                regions.union(edge.source().index(), edge.target().index());
+            }
+        }
+        let labels = regions.into_labeling();
+        let label = |room: Room| labels[grid.ids_by_room[&room].index()];
+        let (start, goal) = (room_at(start), room_at(goal));
+        let mut warnings = Vec::new();
+        if label(start) != label(goal) {
+            warnings.push(LevelWarning::UnreachableGoal);
+        }
+        // The first room of each group, as drawn, and how many it has.
+        let mut disconnected = HashMap::<usize, ((usize, usize), usize)>::new();
+        for room in grid.graph.node_weights() {
+            if label(*room) != label(start) {
+                let (first, rooms) = disconnected
+                    .entry(label(*room))
+                    .or_insert((position(*room), 0));
+                *first = position(*room).min(*first);
+                *rooms += 1;
+            }
+        }
+        let mut disconnected = disconnected.into_values().collect::<Vec<_>>();
+        disconnected.sort();
+        warnings.extend(disconnected.into_iter().map(|((line, column), rooms)| {
+            LevelWarning::DisconnectedRegion {
+                line: line + 1,
+                column: column + 1,
+                rooms,
+            }
+        }));
+
+        let spec = MazeSpec {
+            topology: Topology::Square,
+            x_range: grid.x_range.clone(),
+            z_range: grid.z_range.clone(),
+            mask: None,
+            floors: 1,
+            algorithm: Algorithm::default(),
+            braid: 0.0,
+            seed: 0,
+        };
+        Ok(Level {
+            layout: MazeLayout {
+                spec,
+                graph: grid.graph,
+                border: grid.border,
+                start,
+                goal,
+                hand_drawn: true,
+            },
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(column: i32, row: i32) -> Room {
+        Room {
+            column,
+            row,
+            floor: 0,
+        }
+    }
+
+    #[test]
+    fn parses_rooms_walls_and_markers() {
+        // The missing room at the bottom right is left out, and the short
+        // last line is padded with walls.
+        let level = Level::parse(
+            "#######\n\
+             #S. . #\n\
+             ##### #\n\
+             #G    #\n\
+             ###",
+        )
+        .unwrap();
+        let layout = &level.layout;
+        assert!(level.warnings.is_empty());
+        assert_eq!(layout.start, room(0, 1));
+        assert_eq!(layout.goal, room(0, 0));
+        assert_eq!(layout.graph.node_count(), 6);
+        let passages = layout
+            .graph
+            .edge_weights()
+            .filter(|wall| matches!(wall.disposition, Disposition::Absent))
+            .count();
+        assert_eq!(passages, 5);
+        assert_eq!(layout.metrics().unwrap().solution_length, 5);
+    }
+
+    #[test]
+    fn errors_say_where() {
+        assert_eq!(
+            Level::parse("###\n#S@\n").unwrap_err(),
+            LevelError::UnexpectedCharacter {
+                line: 2,
+                column: 3,
+                character: '@',
+            }
+        );
+        assert_eq!(
+            Level::parse("#####\n#SG #\n#####").unwrap_err(),
+            LevelError::MarkerOutsideRoom {
+                line: 2,
+                column: 3,
+                marker: 'G',
+            }
+        );
+        assert_eq!(
+            Level::parse("#####\n#S S#\n#####").unwrap_err(),
+            LevelError::DuplicateMarker {
+                line: 2,
+                column: 4,
+                marker: 'S',
+            }
+        );
+        assert_eq!(
+            Level::parse("#####\n#S  #\n#####").unwrap_err(),
+            LevelError::MissingMarker('G')
+        );
+        assert_eq!(
+            Level::parse("#####\n#  G#\n#####").unwrap_err(),
+            LevelError::MissingMarker('S')
+        );
+    }
+
+    #[test]
+    fn warns_about_rooms_the_start_cant_reach() {
+        let level = Level::parse(
+            "#########\n\
+             #S  #   #\n\
+             #####   #\n\
+             #G# #   #\n\
+             #########",
+        )
+        .unwrap();
+        assert_eq!(
+            level.warnings,
+            [
+                LevelWarning::UnreachableGoal,
+                LevelWarning::DisconnectedRegion {
+                    line: 2,
+                    column: 6,
+                    rooms: 4,
+                },
+                LevelWarning::DisconnectedRegion {
+                    line: 4,
+                    column: 2,
+                    rooms: 1,
+                },
+                LevelWarning::DisconnectedRegion {
+                    line: 4,
+                    column: 4,
+                    rooms: 1,
+                },
+            ]
+        );
+        assert_eq!(level.layout.metrics(), None);
+    }
+}
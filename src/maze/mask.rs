@@ -0,0 +1,249 @@
+//! Masks that cut a maze into a shape other than the full grid.
+//!
+//! A mask is a small picture of which rooms to keep.  It gets stretched over
+//! the whole maze, whatever its topology, so the same mask can shape a 10×10
+//! maze or a 50×50 one.  Its top row lies along the maze's north edge (`+z`),
+//! and each row runs west to east (`+x`).
+
+use std::{error::Error, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Room, Topology};
+
+/// Saved mazes store their mask as text, one string per row.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "Vec<String>", try_from = "Vec<String>")]
+pub struct Mask {
+    width: usize,
+    height: usize,
+    /// Whether each cell is kept, row by row from the top.
+    cells: Vec<bool>,
+}
+
+#[derive(Debug)]
+pub enum MaskError {
+    /// The mask has no cells at all, or keeps none of them.
+    Empty,
+    /// The mask keeps cells, but none of them land on a room of the maze
+    /// it's stretched over.  That happens when the maze is too small to show
+    /// the mask's shape.
+    NoRooms,
+    /// ASCII masks have to be UTF-8.
+    NotText(std::str::Utf8Error),
+    Png(png::DecodingError),
+}
+
+impl fmt::Display for MaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskError::Empty => write!(f, "the mask is empty"),
+            MaskError::NoRooms => write!(f, "the mask leaves none of the maze's rooms"),
+            MaskError::NotText(err) => write!(f, "the mask isn't a PNG or UTF-8 text: {err}"),
+            MaskError::Png(err) => write!(f, "couldn't decode the mask's PNG: {err}"),
+        }
+    }
+}
+
+impl Error for MaskError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MaskError::Empty | MaskError::NoRooms => None,
+            MaskError::NotText(err) => Some(err),
+            MaskError::Png(err) => Some(err),
+        }
+    }
+}
+
+impl From<png::DecodingError> for MaskError {
+    fn from(err: png::DecodingError) -> Self {
+        MaskError::Png(err)
+    }
+}
+
+impl From<Mask> for Vec<String> {
+    /// Draws the mask the way [`Mask::from_ascii`] reads it, with `#` for
+    /// kept cells and `.` for the rest.
+    fn from(mask: Mask) -> Self {
+        mask.cells
+            .chunks(mask.width)
+            .map(|row| {
+                row.iter()
+                    .map(|&kept| if kept { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<Vec<String>> for Mask {
+    type Error = MaskError;
+
+    fn try_from(rows: Vec<String>) -> Result<Self, Self::Error> {
+        Self::from_ascii(&rows.join("\n"))
+    }
+}
+
+/// Every PNG file starts with these bytes.
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+impl Mask {
+    /// Reads a mask from the contents of a PNG file or an ASCII one,
+    /// whichever `bytes` turn out to be.
+    pub fn parse(bytes: &[u8]) -> Result<Self, MaskError> {
+        if bytes.starts_with(&PNG_SIGNATURE) {
+            Self::from_png(bytes)
+        } else {
+            Self::from_ascii(std::str::from_utf8(bytes).map_err(MaskError::NotText)?)
+        }
+    }
+
+    /// Reads a mask drawn in text, one line per row.  Spaces and `.` leave a
+    /// cell out; any other character keeps it.  Short lines are padded with
+    /// left-out cells, and blank lines at the end are ignored.
+    pub fn from_ascii(text: &str) -> Result<Self, MaskError> {
+        let mut lines = text.lines().map(str::trim_end).collect::<Vec<_>>();
+        while lines.last() == Some(&"") {
+            lines.pop();
+        }
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let cells = lines
+            .iter()
+            .flat_map(|line| {
+                line.chars()
+                    .map(|c| c != ' ' && c != '.')
+                    .chain(std::iter::repeat(false))
+                    .take(width)
+            })
+            .collect();
+        Self::new(width, lines.len(), cells)
+    }
+
+    /// Reads a mask from a PNG image, one pixel per cell.  Dark, opaque
+    /// pixels keep their cell; light or transparent ones leave it out.  So
+    /// draw the shape in black on white or on a transparent background.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, MaskError> {
+        let mut decoder = png::Decoder::new(bytes);
+        // Expand palettes and low bit depths, and drop 16-bit precision, so
+        // every pixel comes out as 8-bit gray or RGB, with or without alpha.
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut pixels)?;
+        let channels = frame.color_type.samples();
+        let cells = pixels[..frame.buffer_size()]
+            .chunks_exact(channels)
+            .map(|pixel| {
+                let (brightness, alpha) = match *pixel {
+                    [gray] => (gray as u32, 255),
+                    [gray, alpha] => (gray as u32, alpha),
+                    [r, g, b] => ((r as u32 + g as u32 + b as u32) / 3, 255),
+                    [r, g, b, alpha] => ((r as u32 + g as u32 + b as u32) / 3, alpha),
+                    _ => unreachable!("8-bit pixels have one to four channels"),
+                };
+                brightness < 128 && alpha >= 128
+            })
+            .collect();
+        Self::new(frame.width as usize, frame.height as usize, cells)
+    }
+
+    fn new(width: usize, height: usize, cells: Vec<bool>) -> Result<Self, MaskError> {
+        if width == 0 || height == 0 || !cells.contains(&true) {
+            return Err(MaskError::Empty);
+        }
+        Ok(Mask {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the cell `column` cells from the left and `row` cells from the
+    /// top is kept.
+    pub fn get(&self, column: usize, row: usize) -> bool {
+        column < self.width && row < self.height && self.cells[row * self.width + column]
+    }
+
+    /// Picks out which of `rooms` the mask keeps, once it's stretched so its
+    /// corner cells land on the outermost room centers.  A mask drawn with one
+    /// cell per room of a square grid lines up with it exactly.
+    pub fn apply(&self, topology: Topology, rooms: Vec<Room>) -> Vec<Room> {
+        let centers = rooms
+            .iter()
+            .map(|room| topology.room_center(*room))
+            .collect::<Vec<_>>();
+        let bounds = |coordinate: fn(&(f32, f32)) -> f32| {
+            centers
+                .iter()
+                .map(coordinate)
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                })
+        };
+        let (x_min, x_max) = bounds(|(x, _)| *x);
+        let (z_min, z_max) = bounds(|(_, z)| *z);
+        // Which of `cells` cells `fraction` of the way across lands in.
+        let cell = |fraction: f32, cells: usize| {
+            let fraction = if fraction.is_finite() { fraction } else { 0.5 };
+            (fraction * (cells - 1) as f32).round() as usize
+        };
+
+        rooms
+            .into_iter()
+            .zip(centers)
+            .filter(|(_, (x, z))| {
+                self.get(
+                    cell((x - x_min) / (x_max - x_min), self.width),
+                    cell((z_max - z) / (z_max - z_min), self.height),
+                )
+            })
+            .map(|(room, _)| room)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{Algorithm, MazeLayout, MazeSpec};
+
+    #[test]
+    fn masks_that_keep_nothing_are_empty() {
+        assert!(matches!(Mask::from_ascii(""), Err(MaskError::Empty)));
+        assert!(matches!(
+            Mask::from_ascii("...\n. ."),
+            Err(MaskError::Empty)
+        ));
+    }
+
+    #[test]
+    fn masks_that_miss_every_room_fail_to_generate() {
+        // Stretched over a 2×2 maze, only the corner cells land on rooms.
+        let spec = MazeSpec {
+            topology: Topology::Square,
+            x_range: 0..=2,
+            z_range: 0..=2,
+            mask: Some(Mask::from_ascii("...\n.#.\n...").unwrap()),
+            floors: 1,
+            algorithm: Algorithm::default(),
+            braid: 0.0,
+            seed: 0,
+        };
+        assert!(matches!(
+            MazeLayout::generate(&spec),
+            Err(MaskError::NoRooms)
+        ));
+    }
+}
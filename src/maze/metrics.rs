@@ -0,0 +1,357 @@
+//! How hard a maze is, in numbers.
+//!
+//! [`MazeLayout::metrics`] measures a maze's shape, and
+//! [`MazeLayout::generate_with_difficulty`] rolls mazes until one comes out
+//! about as hard as asked for.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+};
+
+use petgraph::visit::EdgeRef;
+
+use super::{
+    other_end, solver, Algorithm, Disposition, MaskError, MazeAlgorithm, MazeLayout, MazeSpec,
+};
+
+/// A report on a maze's shape.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Metrics {
+    pub rooms: usize,
+    /// Steps along the shortest way from the start to the goal.
+    pub solution_length: usize,
+    /// Rooms with only one way in or out.
+    pub dead_ends: usize,
+    /// The share of rooms where the way splits, with three or more
+    /// passages.
+    pub branching_factor: f32,
+    /// The share of rooms with exactly two passages.  It's high in mazes of
+    /// long, winding corridors (like the recursive backtracker's), and low
+    /// in bushy ones (like Prim's).
+    pub river_factor: f32,
+    /// Rooms on the way from the start to the goal, besides the goal, where
+    /// there's more than one way onward.
+    pub decision_points: usize,
+    /// The farthest any room is from the way from the start to the goal, in
+    /// steps.  That's how far a wrong turn can lead.
+    pub longest_dead_end: usize,
+}
+
+/// How hard a generated maze should be.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Metrics {
+    /// A score from 0 (a straight corridor) to 1, averaging how many of the
+    /// rooms on the way to the goal are decisions, how much of the maze the
+    /// way to the goal covers, and how long wrong turns get compared to the
+    /// right way.
+    pub fn difficulty(&self) -> f32 {
+        let solution_rooms = (self.solution_length + 1) as f32;
+        let decisions = self.decision_points as f32 / solution_rooms;
+        let coverage = solution_rooms / self.rooms as f32;
+        let detours = self.longest_dead_end as f32
+            / (self.longest_dead_end + self.solution_length).max(1) as f32;
+        (decisions + coverage + detours) / 3.0
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Rooms: {}", self.rooms)?;
+        writeln!(f, "Solution length: {} steps", self.solution_length)?;
+        writeln!(f, "Dead ends: {}", self.dead_ends)?;
+        writeln!(f, "Branching factor: {:.0}%", self.branching_factor * 100.0)?;
+        writeln!(f, "River factor: {:.0}%", self.river_factor * 100.0)?;
+        writeln!(f, "Decision points: {}", self.decision_points)?;
+        writeln!(f, "Longest dead end: {} steps", self.longest_dead_end)?;
+        write!(f, "Difficulty score: {:.3}", self.difficulty())
+    }
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Medium,
+            Difficulty::Medium => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// The scores from `Metrics::difficulty` that count as this difficulty.
+    pub fn band(self) -> (f32, f32) {
+        match self {
+            Difficulty::Easy => (0.0, EASY_MAX),
+            Difficulty::Medium => (EASY_MAX, HARD_MIN),
+            Difficulty::Hard => (HARD_MIN, 1.0),
+        }
+    }
+
+    /// How far `score` is from this difficulty's band; zero if it's in it.
+    fn miss(self, score: f32) -> f32 {
+        let (min, max) = self.band();
+        (min - score).max(score - max).max(0.0)
+    }
+}
+
+/// Where `Difficulty::band`s meet.
+const EASY_MAX: f32 = 0.28;
+const HARD_MIN: f32 = 0.34;
+
+/// How many seeds `generate_with_difficulty` tries with each algorithm.
+const SEEDS_PER_ALGORITHM: u64 = 8;
+
+/// How many rooms `generate_with_difficulty` generates in all before it
+/// settles for the closest maze so far.  Small mazes get every attempt, but
+/// the biggest ones only get a few, since each takes a good fraction of a
+/// second.
+const ROOM_BUDGET: usize = 1_000_000;
+
+impl MazeLayout {
+    /// Measures the maze.  `None` if there's no way from the start to the
+    /// goal.
+    pub fn metrics(&self) -> Option<Metrics> {
+        let solution = solver::breadth_first(self, self.start, self.goal)?;
+        let passages = self
+            .graph
+            .node_indices()
+            .map(|room| {
+                self.graph
+                    .edges(room)
+                    .filter(|edge| edge.weight().disposition == Disposition::Absent)
+                    .count()
+            })
+            .collect::<Vec<_>>();
+        let rooms = self.graph.node_count();
+        let share = |count: usize| count as f32 / rooms as f32;
+        let with_passages = |wanted: &dyn Fn(usize) -> bool| {
+            passages.iter().filter(|count| wanted(**count)).count()
+        };
+
+        // Only the start has no way back to count out.
+        let on_solution = solution.iter().collect::<HashSet<_>>();
+        let ids = self
+            .graph
+            .node_indices()
+            .filter(|id| on_solution.contains(&self.graph[*id]))
+            .collect::<Vec<_>>();
+        let decision_points = ids
+            .iter()
+            .filter(|id| {
+                let room = self.graph[**id];
+                let onward = passages[id.index()] - usize::from(room != self.start);
+                room != self.goal && onward > 1
+            })
+            .count();
+
+        // Spread out from the whole solution at once.
+        let mut distances = vec![None; rooms];
+        let mut frontier = VecDeque::new();
+        for id in ids {
+            distances[id.index()] = Some(0);
+            frontier.push_back(id);
+        }
+        while let Some(room) = frontier.pop_front() {
+            let distance = distances[room.index()].unwrap();
+            for edge in self.graph.edges(room) {
+                let neighbor = other_end(edge.source(), edge.target(), room);
+                if edge.weight().disposition == Disposition::Absent
+                    && distances[neighbor.index()].is_none()
+                {
+                    distances[neighbor.index()] = Some(distance + 1);
+                    frontier.push_back(neighbor);
+                }
+            }
+        }
+
+        Some(Metrics {
+            rooms,
+            solution_length: solution.len() - 1,
+            dead_ends: with_passages(&|count| count == 1),
+            branching_factor: share(with_passages(&|count| count >= 3)),
+            river_factor: share(with_passages(&|count| count == 2)),
+            decision_points,
+            longest_dead_end: distances.into_iter().flatten().max().unwrap_or(0),
+        })
+    }
+
+    /// Generates a maze like `spec`'s, as close to `difficulty` as it can.
+    ///
+    /// It tries a handful of seeds (picked from `spec.seed`), and then the
+    /// other algorithms that can generate the spec's mazes, keeping
+    /// whichever maze comes closest, until it's generated `ROOM_BUDGET`
+    /// rooms.  The maze's own spec says what it was generated with, so
+    /// generating that spec again gives the same maze.  Fails like
+    /// [`MazeLayout::generate`] does.
+    pub fn generate_with_difficulty(
+        spec: &MazeSpec,
+        difficulty: Difficulty,
+    ) -> Result<Self, MaskError> {
+        let algorithms = std::iter::once(spec.algorithm).chain(
+            Algorithm::ALL
+                .into_iter()
+                .filter(|algorithm| *algorithm != spec.algorithm && algorithm.supports(spec)),
+        );
+        let attempts = algorithms.flat_map(|algorithm| {
+            (0..SEEDS_PER_ALGORITHM).map(move |attempt| (algorithm, attempt))
+        });
+        let mut best: Option<(f32, MazeLayout)> = None;
+        let mut rooms = 0;
+        for (algorithm, attempt) in attempts {
+            if rooms >= ROOM_BUDGET {
+                break;
+            }
+            let layout = MazeLayout::generate(&MazeSpec {
+                algorithm,
+                seed: spec.seed.wrapping_add(attempt),
+                ..spec.clone()
+            })?;
+            rooms += layout.graph.node_count();
+            let miss = layout.metrics().map_or(f32::INFINITY, |metrics| {
+                difficulty.miss(metrics.difficulty())
+            });
+            if miss == 0.0 {
+                return Ok(layout);
+            }
+            if best.as_ref().filter(|(best, _)| *best <= miss).is_none() {
+                best = Some((miss, layout));
+            }
+        }
+        Ok(best.expect("there's always at least one attempt").1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{Level, Topology};
+
+    fn metrics(level: &str) -> Metrics {
+        Level::parse(level).unwrap().layout.metrics().unwrap()
+    }
+
+    #[test]
+    fn corridor() {
+        let metrics = metrics(
+            "#########\n\
+             #S     G#\n\
+             #########",
+        );
+        assert_eq!(metrics.rooms, 4);
+        assert_eq!(metrics.solution_length, 3);
+        assert_eq!(metrics.dead_ends, 2);
+        assert_eq!(metrics.branching_factor, 0.0);
+        assert_eq!(metrics.river_factor, 0.5);
+        assert_eq!(metrics.decision_points, 0);
+        assert_eq!(metrics.longest_dead_end, 0);
+        // No decisions, the whole maze on the way, and no detours.
+        assert_eq!(metrics.difficulty(), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn branches() {
+        let metrics = metrics(
+            "#######\n\
+             #S    #\n\
+             ### ###\n\
+             #G    #\n\
+             #######",
+        );
+        assert_eq!(metrics.rooms, 6);
+        assert_eq!(metrics.solution_length, 3);
+        assert_eq!(metrics.dead_ends, 4);
+        assert_eq!(metrics.branching_factor, 2.0 / 6.0);
+        assert_eq!(metrics.river_factor, 0.0);
+        // Both rooms in the middle have a wrong turn; the start doesn't.
+        assert_eq!(metrics.decision_points, 2);
+        assert_eq!(metrics.longest_dead_end, 1);
+    }
+
+    #[test]
+    fn long_dead_end() {
+        let metrics = metrics(
+            "#########\n\
+             #S   G  #\n\
+             ####### #\n\
+             #       #\n\
+             #########",
+        );
+        assert_eq!(metrics.rooms, 8);
+        assert_eq!(metrics.solution_length, 2);
+        assert_eq!(metrics.dead_ends, 2);
+        assert_eq!(metrics.river_factor, 6.0 / 8.0);
+        // Going on past the goal isn't a decision.
+        assert_eq!(metrics.decision_points, 0);
+        assert_eq!(metrics.longest_dead_end, 5);
+    }
+
+    #[test]
+    fn unreachable_goal_has_no_metrics() {
+        let level = Level::parse("#####\n#S#G#\n#####").unwrap();
+        assert_eq!(level.layout.metrics(), None);
+    }
+
+    #[test]
+    fn difficulty_bands() {
+        let metrics = |solution_length, decision_points, longest_dead_end| Metrics {
+            rooms: 100,
+            solution_length,
+            dead_ends: 0,
+            branching_factor: 0.0,
+            river_factor: 0.0,
+            decision_points,
+            longest_dead_end,
+        };
+        let band = |metrics: Metrics| {
+            let score = metrics.difficulty();
+            Difficulty::ALL
+                .into_iter()
+                .filter(|difficulty| difficulty.miss(score) == 0.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(band(metrics(9, 0, 1)), [Difficulty::Easy]);
+        assert_eq!(band(metrics(29, 9, 10)), [Difficulty::Medium]);
+        assert_eq!(band(metrics(49, 25, 30)), [Difficulty::Hard]);
+
+        // The bands cover every score, without gaps.
+        assert_eq!(Difficulty::Easy.band().0, 0.0);
+        assert_eq!(Difficulty::Easy.band().1, Difficulty::Medium.band().0);
+        assert_eq!(Difficulty::Medium.band().1, Difficulty::Hard.band().0);
+        assert_eq!(Difficulty::Hard.band().1, 1.0);
+    }
+
+    #[test]
+    fn generate_with_difficulty_lands_in_the_band() {
+        for difficulty in Difficulty::ALL {
+            let spec = MazeSpec {
+                topology: Topology::Square,
+                x_range: -5..=5,
+                z_range: -5..=5,
+                mask: None,
+                floors: 1,
+                algorithm: Algorithm::default(),
+                braid: 0.0,
+                seed: 0x5eed_f00d,
+            };
+            let layout = MazeLayout::generate_with_difficulty(&spec, difficulty).unwrap();
+            let score = layout.metrics().unwrap().difficulty();
+            assert_eq!(difficulty.miss(score), 0.0, "{difficulty:?} scored {score}");
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Finding the way through a maze.
+//!
+//! Every solver walks the maze graph through passages only (absent walls,
+//! including ramps between floors) and returns the rooms it passes through,
+//! from the first room to the last.  They return `None` when they can't get
+//! there: when there's no way through at all, or when a wall follower ends
+//! up going around in circles.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    f32::consts::TAU,
+};
+
+use petgraph::{
+    algo::astar,
+    graph::{EdgeIndex, NodeIndex},
+    visit::{EdgeFiltered, EdgeRef},
+};
+
+use super::{other_end, Disposition, MazeLayout, Room};
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Strategy {
+    /// The path through the fewest rooms.
+    BreadthFirst,
+    /// The shortest path as the crow walks: from room center to room
+    /// center, by way of the middle of each passage.
+    AStar,
+    /// Keep your left hand on the wall.
+    LeftHand,
+    /// Keep your right hand on the wall.
+    RightHand,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 4] = [
+        Strategy::BreadthFirst,
+        Strategy::AStar,
+        Strategy::LeftHand,
+        Strategy::RightHand,
+    ];
+
+    /// A human-readable name, for menus and the HUD.
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::BreadthFirst => "Breadth-first",
+            Strategy::AStar => "A*",
+            Strategy::LeftHand => "Left hand",
+            Strategy::RightHand => "Right hand",
+        }
+    }
+}
+
+/// Finds a way from `from` to `to` with the given strategy.
+pub fn solve(layout: &MazeLayout, strategy: Strategy, from: Room, to: Room) -> Option<Vec<Room>> {
+    match strategy {
+        Strategy::BreadthFirst => breadth_first(layout, from, to),
+        Strategy::AStar => a_star(layout, from, to),
+        Strategy::LeftHand => follow_wall(layout, Hand::Left, from, to),
+        Strategy::RightHand => follow_wall(layout, Hand::Right, from, to),
+    }
+}
+
+/// The path from `from` to `to` through the fewest rooms.
+pub fn breadth_first(layout: &MazeLayout, from: Room, to: Room) -> Option<Vec<Room>> {
+    let (from, to) = (find(layout, from)?, find(layout, to)?);
+    // How each room was first reached.
+    let mut came_from = HashMap::from([(from, from)]);
+    let mut frontier = VecDeque::from([from]);
+    while let Some(room) = frontier.pop_front() {
+        if room == to {
+            break;
+        }
+        for (neighbor, _) in passages(layout, room) {
+            came_from.entry(neighbor).or_insert_with(|| {
+                frontier.push_back(neighbor);
+                room
+            });
+        }
+    }
+
+    let mut path = vec![to];
+    while *path.last().unwrap() != from {
+        path.push(*came_from.get(path.last().unwrap())?);
+    }
+    path.reverse();
+    Some(path.into_iter().map(|room| layout.graph[room]).collect())
+}
+
+/// The shortest path from `from` to `to` by distance walked.  Climbing a
+/// ramp counts as walking across one room.
+pub fn a_star(layout: &MazeLayout, from: Room, to: Room) -> Option<Vec<Room>> {
+    let (from, to) = (find(layout, from)?, find(layout, to)?);
+    let topology = layout.spec.topology;
+    let center = |room: NodeIndex| topology.room_center(layout.graph[room]);
+    let distance = |(x0, z0): (f32, f32), (x1, z1): (f32, f32)| (x1 - x0).hypot(z1 - z0);
+    let open = EdgeFiltered::from_fn(&layout.graph, |edge| {
+        edge.weight().disposition == Disposition::Absent
+    });
+    // Straight-line distance along the floor never overestimates, since no
+    // step costs less than the ground it covers.
+    let (_, path) = astar(
+        &open,
+        from,
+        |room| room == to,
+        |edge| {
+            let wall = edge.weight();
+            if wall.side.is_vertical() {
+                return 1.0;
+            }
+            let middle = topology.wall_midpoint(wall);
+            distance(center(edge.source()), middle) + distance(middle, center(edge.target()))
+        },
+        |room| distance(center(room), center(to)),
+    )?;
+    Some(path.into_iter().map(|room| layout.graph[room]).collect())
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+enum Hand {
+    Left,
+    Right,
+}
+
+/// Walks from `from` with one hand on the wall until it reaches `to`, and
+/// returns every room along the way, dead ends and doubling back included.
+fn follow_wall(layout: &MazeLayout, hand: Hand, from: Room, to: Room) -> Option<Vec<Room>> {
+    let (from, to) = (find(layout, from)?, find(layout, to)?);
+    let mut room = from;
+    // Which passage led into `room`.  At the start, pretend to have come in
+    // from the south.
+    let mut entrance = None;
+    let mut path = vec![layout.graph[from]];
+    // Coming back into a room the same way as before means going in circles.
+    let mut seen = HashSet::new();
+    while room != to {
+        if !seen.insert((room, entrance)) {
+            return None;
+        }
+        let behind = entrance.map_or(-TAU / 4.0, |wall| passage_angle(layout, room, wall));
+        // How far the hand side has to turn from facing back the way we came
+        // to face each passage.  Going back is the last resort.
+        let turn = |wall: EdgeIndex| {
+            let angle = passage_angle(layout, room, wall);
+            let turn = match hand {
+                Hand::Left => (behind - angle).rem_euclid(TAU),
+                Hand::Right => (angle - behind).rem_euclid(TAU),
+            };
+            if Some(wall) == entrance || turn == 0.0 {
+                TAU
+            } else {
+                turn
+            }
+        };
+        let (next, wall) =
+            passages(layout, room).min_by(|(_, a), (_, b)| turn(*a).total_cmp(&turn(*b)))?;
+        room = next;
+        entrance = Some(wall);
+        path.push(layout.graph[room]);
+    }
+    Some(path)
+}
+
+/// Which way `wall` is from the center of `room`, as an angle on the `x`/`z`
+/// plane.  A ramp leaves from the room's passage and climbs across the room,
+/// so it points away from that passage.
+fn passage_angle(layout: &MazeLayout, room: NodeIndex, wall: EdgeIndex) -> f32 {
+    let topology = layout.spec.topology;
+    let (x, z) = topology.room_center(layout.graph[room]);
+    let angle_to = |wall: EdgeIndex| {
+        let (wall_x, wall_z) = topology.wall_midpoint(&layout.graph[wall]);
+        (wall_z - z).atan2(wall_x - x)
+    };
+    if !layout.graph[wall].side.is_vertical() {
+        return angle_to(wall);
+    }
+    passages(layout, room)
+        .find(|(_, other)| !layout.graph[*other].side.is_vertical())
+        .map_or(0.0, |(_, other)| angle_to(other) + TAU / 2.0)
+}
+
+/// The rooms next to `room` that there's a way through to, each with the
+/// wall in between.
+fn passages(
+    layout: &MazeLayout,
+    room: NodeIndex,
+) -> impl Iterator<Item = (NodeIndex, EdgeIndex)> + '_ {
+    layout
+        .graph
+        .edges(room)
+        .filter(|edge| edge.weight().disposition == Disposition::Absent)
+        .map(move |edge| (other_end(edge.source(), edge.target(), room), edge.id()))
+}
+
+fn find(layout: &MazeLayout, room: Room) -> Option<NodeIndex> {
+    layout
+        .graph
+        .node_indices()
+        .find(|id| layout.graph[*id] == room)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{Algorithm, Level, MazeSpec, Topology};
+
+    fn layout(seed: u64, braid: f32) -> MazeLayout {
+        MazeLayout::generate(&MazeSpec {
+            topology: Topology::Square,
+            x_range: -4..=4,
+            z_range: -4..=4,
+            mask: None,
+            floors: 1,
+            algorithm: Algorithm::default(),
+            braid,
+            seed,
+        })
+        .unwrap()
+    }
+
+    /// Whether every step of `path` goes through a passage.
+    fn walkable(layout: &MazeLayout, path: &[Room]) -> bool {
+        path.windows(2).all(|step| {
+            let (from, to) = (
+                find(layout, step[0]).unwrap(),
+                find(layout, step[1]).unwrap(),
+            );
+            passages(layout, from).any(|(room, _)| room == to)
+        })
+    }
+
+    #[test]
+    fn breadth_first_and_a_star_are_equally_short() {
+        // On a square grid every step is as long as every other, so the
+        // path through the fewest rooms is also the shortest walk, loops or
+        // not.
+        for seed in 0..20 {
+            let layout = layout(seed, 0.5);
+            let (start, goal) = (layout.start, layout.goal);
+            let bfs = breadth_first(&layout, start, goal).unwrap();
+            let a_star = a_star(&layout, start, goal).unwrap();
+            assert_eq!(bfs.len(), a_star.len(), "seed {seed}");
+            assert!(walkable(&layout, &bfs) && walkable(&layout, &a_star));
+        }
+    }
+
+    #[test]
+    fn wall_followers_solve_perfect_mazes() {
+        for seed in 0..20 {
+            let layout = layout(seed, 0.0);
+            for strategy in [Strategy::LeftHand, Strategy::RightHand] {
+                let path = solve(&layout, strategy, layout.start, layout.goal)
+                    .unwrap_or_else(|| panic!("{strategy:?} got lost with seed {seed}"));
+                assert_eq!(path.first(), Some(&layout.start));
+                assert_eq!(path.last(), Some(&layout.goal));
+                assert!(walkable(&layout, &path));
+            }
+        }
+    }
+
+    #[test]
+    fn wall_followers_stop_going_in_circles() {
+        // The goal hangs off a pillar in the middle of a ring, so the hand
+        // that ends up on the outer wall walks around the ring forever.
+        let level = Level::parse(
+            "#######\n\
+             #     #\n\
+             # # # #\n\
+             # #G# #\n\
+             # ### #\n\
+             #S    #\n\
+             #######",
+        )
+        .unwrap();
+        let ring = &level.layout;
+        let paths = [Strategy::LeftHand, Strategy::RightHand]
+            .map(|strategy| solve(ring, strategy, ring.start, ring.goal));
+        assert!(paths.iter().any(Option::is_none));
+        for path in paths.iter().flatten() {
+            assert_eq!(path.last(), Some(&ring.goal));
+        }
+
+        // Braided mazes have loops all over; every walk has to end.
+        for seed in 0..20 {
+            let layout = layout(seed, 1.0);
+            for strategy in [Strategy::LeftHand, Strategy::RightHand] {
+                if let Some(path) = solve(&layout, strategy, layout.start, layout.goal) {
+                    assert_eq!(path.last(), Some(&layout.goal));
+                }
+            }
+        }
+    }
+}
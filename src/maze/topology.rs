@@ -0,0 +1,390 @@
+//! The shapes rooms can have, and how they fit together.
+//!
+//! Positions here are in "room units": neighboring room centers are roughly
+//! one unit apart, and the spawner scales everything by the room size.  `x`
+//! and `z` match the world axes, so "north" is `+z` and "east" is `+x`.
+
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Room, Wall};
+
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    /// Square rooms on a square lattice.  Room `(column, row)` spans
+    /// `column..column + 1` along `x` and `row..row + 1` along `z`.
+    #[default]
+    Square,
+    /// Pointy-topped hexagonal rooms.  Odd rows are shifted half a room east,
+    /// so every room shares an edge with the rooms directly above and below
+    /// it in `(column, row)` terms.
+    Hex,
+    /// Concentric rings of rooms around a round room at the origin.  `row`
+    /// counts rings outward from the center (which is ring 0), and `column`
+    /// counts rooms counterclockwise around the ring from `+x`.  Rings split
+    /// their rooms in two whenever they'd otherwise get too wide.
+    Polar,
+}
+
+/// One side of a room.  Each topology only uses some of these.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+    /// Towards the center of a polar maze.
+    Inward,
+    /// Away from the center of a polar maze.  A room may have several
+    /// neighbors on this side, where the next ring splits its rooms.
+    Outward,
+    Clockwise,
+    Counterclockwise,
+    /// The ceiling, which is the next floor up's floor.
+    Up,
+    /// The floor.
+    Down,
+}
+
+impl Side {
+    /// Whether this side leads to another floor rather than along this one.
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Side::Up | Side::Down)
+    }
+
+    /// The side a neighbor sees this side's wall from.
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::North => Side::South,
+            Side::South => Side::North,
+            Side::East => Side::West,
+            Side::West => Side::East,
+            Side::NorthEast => Side::SouthWest,
+            Side::NorthWest => Side::SouthEast,
+            Side::SouthEast => Side::NorthWest,
+            Side::SouthWest => Side::NorthEast,
+            Side::Inward => Side::Outward,
+            Side::Outward => Side::Inward,
+            Side::Clockwise => Side::Counterclockwise,
+            Side::Counterclockwise => Side::Clockwise,
+            Side::Up => Side::Down,
+            Side::Down => Side::Up,
+        }
+    }
+}
+
+/// The distance from a hex room's center to each of its corners.
+const HEX_RADIUS: f32 = 0.577_350_26; // 1 / sqrt(3)
+
+/// The distance between the centers of adjacent hex rows.
+const HEX_ROW_SPACING: f32 = 1.5 * HEX_RADIUS;
+
+/// How many straight segments make up a full circle of curved wall.
+const ARC_SEGMENTS_PER_TURN: f32 = 48.0;
+
+impl Topology {
+    pub const ALL: [Topology; 3] = [Topology::Square, Topology::Hex, Topology::Polar];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Square => "Square",
+            Topology::Hex => "Hex",
+            Topology::Polar => "Polar",
+        }
+    }
+
+    /// The topology after this one in [`Topology::ALL`], wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|t| *t == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Every side of `room` along its floor, counterclockwise (as seen on the
+    /// `x`/`z` plane) starting from east, or from outward for polar mazes.
+    pub fn sides(self, room: Room) -> &'static [Side] {
+        match self {
+            Topology::Square => &[Side::East, Side::North, Side::West, Side::South],
+            Topology::Hex => &[
+                Side::East,
+                Side::NorthEast,
+                Side::NorthWest,
+                Side::West,
+                Side::SouthWest,
+                Side::SouthEast,
+            ],
+            Topology::Polar if room.row == 0 => &[Side::Outward],
+            Topology::Polar => &[
+                Side::Outward,
+                Side::Counterclockwise,
+                Side::Inward,
+                Side::Clockwise,
+            ],
+        }
+    }
+
+    /// The sides along the floor whose walls belong to the room, rather than
+    /// to the neighbor on the other side.  Every interior wall belongs to
+    /// exactly one room.  (Floors belong to the room above them.)
+    pub fn owned_sides(self) -> &'static [Side] {
+        match self {
+            Topology::Square => &[Side::South, Side::West],
+            Topology::Hex => &[Side::West, Side::SouthWest, Side::SouthEast],
+            Topology::Polar => &[Side::Inward, Side::Clockwise],
+        }
+    }
+
+    /// The room on the other side of `room`'s `side`.  It may not be part of
+    /// the maze.  Where a polar ring splits its rooms, this is the most
+    /// clockwise of the outward neighbors; `neighbors` lists them all.
+    pub fn neighbor(self, room: Room, side: Side) -> Room {
+        let Room { column, row, floor } = room;
+        let (column, row) = match (self, side) {
+            (_, Side::Up) => {
+                return Room {
+                    floor: floor + 1,
+                    ..room
+                }
+            }
+            (_, Side::Down) => {
+                return Room {
+                    floor: floor - 1,
+                    ..room
+                }
+            }
+            (Topology::Square, Side::North) => (column, row + 1),
+            (Topology::Square, Side::South) => (column, row - 1),
+            (Topology::Square, Side::East) => (column + 1, row),
+            (Topology::Square, Side::West) => (column - 1, row),
+            (Topology::Hex, Side::East) => (column + 1, row),
+            (Topology::Hex, Side::West) => (column - 1, row),
+            (Topology::Hex, _) => {
+                // Rows alternate between being shifted west and east of
+                // their neighbors.
+                let shift = row.rem_euclid(2);
+                match side {
+                    Side::NorthEast => (column + shift, row + 1),
+                    Side::NorthWest => (column + shift - 1, row + 1),
+                    Side::SouthEast => (column + shift, row - 1),
+                    Side::SouthWest => (column + shift - 1, row - 1),
+                    _ => panic!("{side:?} isn't a side of a hex room"),
+                }
+            }
+            (Topology::Polar, Side::Outward) => {
+                let split = polar_ring_size(row + 1) / polar_ring_size(row);
+                (column * split, row + 1)
+            }
+            (Topology::Polar, Side::Inward) => {
+                let split = polar_ring_size(row) / polar_ring_size(row - 1);
+                (column / split, row - 1)
+            }
+            (Topology::Polar, Side::Clockwise) => {
+                ((column - 1).rem_euclid(polar_ring_size(row)), row)
+            }
+            (Topology::Polar, Side::Counterclockwise) => {
+                ((column + 1).rem_euclid(polar_ring_size(row)), row)
+            }
+            (Topology::Square, _) => panic!("{side:?} isn't a side of a square room"),
+            (Topology::Polar, _) => panic!("{side:?} isn't a side of a polar room"),
+        };
+        Room { column, row, floor }
+    }
+
+    /// Every room on the other side of `room`'s `side`, counterclockwise.
+    /// That's just the one [`Topology::neighbor`], except where a polar ring
+    /// splits its rooms.  They may not be part of the maze.
+    pub fn neighbors(self, room: Room, side: Side) -> Vec<Room> {
+        let first = self.neighbor(room, side);
+        if (self, side) != (Topology::Polar, Side::Outward) {
+            return vec![first];
+        }
+        let split = polar_ring_size(room.row + 1) / polar_ring_size(room.row);
+        (0..split)
+            .map(|offset| Room {
+                column: first.column + offset,
+                ..first
+            })
+            .collect()
+    }
+
+    pub fn room_center(self, room: Room) -> (f32, f32) {
+        match self {
+            Topology::Square => (room.column as f32 + 0.5, room.row as f32 + 0.5),
+            Topology::Hex => (
+                room.column as f32 + 0.25 + 0.5 * room.row.rem_euclid(2) as f32,
+                (room.row as f32 + 0.5) * HEX_ROW_SPACING,
+            ),
+            Topology::Polar if room.row == 0 => (0.0, 0.0),
+            Topology::Polar => {
+                let (start, end) = polar_angles(room);
+                polar_point(room.row as f32 + 0.5, (start + end) / 2.0)
+            }
+        }
+    }
+
+    /// The room whose floor `point` lies on, on `floor`.  That may not be
+    /// part of the maze.
+    pub fn room_containing(self, (x, z): (f32, f32), floor: i32) -> Room {
+        let (column, row) = match self {
+            Topology::Square => (x.floor() as i32, z.floor() as i32),
+            Topology::Hex => {
+                // Hex rooms don't tile in rows of boxes, so find the nearest
+                // center among the rows the point could be in.
+                let middle_row = (z / HEX_ROW_SPACING - 0.5).round() as i32;
+                (middle_row - 1..=middle_row + 1)
+                    .map(|row| {
+                        let shift = 0.25 + 0.5 * row.rem_euclid(2) as f32;
+                        let column = (x - shift).round() as i32;
+                        let (center_x, center_z) = self.room_center(Room { column, row, floor });
+                        ((column, row), (center_x - x).hypot(center_z - z))
+                    })
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(position, _)| position)
+                    .unwrap()
+            }
+            Topology::Polar => {
+                let ring = x.hypot(z).floor() as i32;
+                let size = polar_ring_size(ring);
+                let turns = z.atan2(x).rem_euclid(TAU) / TAU;
+                (((turns * size as f32) as i32).min(size - 1), ring)
+            }
+        };
+        Room { column, row, floor }
+    }
+
+    /// The points where walls of `room` meet, which is where corner posts
+    /// go.  For square and hex rooms, corner `i` is the clockwise end of the
+    /// wall on side `self.sides(room)[i]`.
+    pub fn room_corners(self, room: Room) -> Vec<(f32, f32)> {
+        let (center_x, center_z) = self.room_center(room);
+        match self {
+            Topology::Square => vec![
+                (center_x + 0.5, center_z - 0.5),
+                (center_x + 0.5, center_z + 0.5),
+                (center_x - 0.5, center_z + 0.5),
+                (center_x - 0.5, center_z - 0.5),
+            ],
+            Topology::Hex => (0..6)
+                .map(|i| {
+                    let angle = TAU * (i as f32 - 0.5) / 6.0;
+                    (
+                        center_x + HEX_RADIUS * angle.cos(),
+                        center_z + HEX_RADIUS * angle.sin(),
+                    )
+                })
+                .collect(),
+            // The center room's edge is lined by the first ring's corners.
+            Topology::Polar if room.row == 0 => vec![],
+            Topology::Polar => {
+                let (start, end) = polar_angles(room);
+                let (inner, outer) = (room.row as f32, room.row as f32 + 1.0);
+                vec![
+                    polar_point(outer, start),
+                    polar_point(outer, end),
+                    polar_point(inner, end),
+                    polar_point(inner, start),
+                ]
+            }
+        }
+    }
+
+    /// The outline of `room`'s floor, counterclockwise (as seen on the
+    /// `x`/`z` plane).  Curved edges are approximated by several points.
+    pub fn room_outline(self, room: Room) -> Vec<(f32, f32)> {
+        match self {
+            Topology::Polar if room.row == 0 => {
+                let mut circle = polar_arc(1.0, 0.0, TAU);
+                circle.pop();
+                circle
+            }
+            Topology::Polar => {
+                let (start, end) = polar_angles(room);
+                let mut outline = polar_arc(room.row as f32 + 1.0, start, end);
+                outline.extend(polar_arc(room.row as f32, start, end).into_iter().rev());
+                outline
+            }
+            _ => self.room_corners(room),
+        }
+    }
+
+    /// The point halfway along `wall`.
+    pub fn wall_midpoint(self, wall: &Wall) -> (f32, f32) {
+        let points = self.wall_points(wall);
+        let middle = points.len() / 2;
+        if points.len() % 2 == 1 {
+            points[middle]
+        } else {
+            let ((x0, z0), (x1, z1)) = (points[middle - 1], points[middle]);
+            ((x0 + x1) / 2.0, (z0 + z1) / 2.0)
+        }
+    }
+
+    /// The path `wall` takes, from one end to the other.  Straight walls
+    /// have two points; curved walls are approximated by several straight
+    /// segments.
+    pub fn wall_points(self, wall: &Wall) -> Vec<(f32, f32)> {
+        if self == Topology::Polar {
+            return polar_wall_points(wall);
+        }
+        let corners = self.room_corners(wall.room);
+        let sides = self.sides(wall.room);
+        let index = sides
+            .iter()
+            .position(|side| *side == wall.side)
+            .unwrap_or_else(|| panic!("{:?} isn't a side of a {self:?} room", wall.side));
+        vec![corners[index], corners[(index + 1) % corners.len()]]
+    }
+}
+
+/// How many rooms there are in a ring of a polar maze.
+pub fn polar_ring_size(ring: i32) -> i32 {
+    // Each ring is one unit deep.  Start with six rooms around the center,
+    // and split rooms in two whenever the ring's inner edge has room for two
+    // (roughly) square rooms per room of the ring inside it.
+    let mut size = 1;
+    for ring in 1..=ring {
+        let inner_circumference = TAU * ring as f32;
+        let split = ((inner_circumference / size as f32).round() as i32).max(1);
+        size *= split;
+    }
+    size
+}
+
+/// The angles (in radians, counterclockwise from `+x`) spanned by a room of
+/// a polar maze.
+fn polar_angles(room: Room) -> (f32, f32) {
+    let size = polar_ring_size(room.row) as f32;
+    (
+        TAU * room.column as f32 / size,
+        TAU * (room.column + 1) as f32 / size,
+    )
+}
+
+fn polar_point(radius: f32, angle: f32) -> (f32, f32) {
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+fn polar_arc(radius: f32, start: f32, end: f32) -> Vec<(f32, f32)> {
+    let segments = ((end - start) / TAU * ARC_SEGMENTS_PER_TURN)
+        .ceil()
+        .max(1.0) as usize;
+    (0..=segments)
+        .map(|i| polar_point(radius, start + (end - start) * i as f32 / segments as f32))
+        .collect()
+}
+
+fn polar_wall_points(wall: &Wall) -> Vec<(f32, f32)> {
+    let (start, end) = polar_angles(wall.room);
+    let (inner, outer) = (wall.room.row as f32, wall.room.row as f32 + 1.0);
+    match wall.side {
+        Side::Outward => polar_arc(outer, start, end),
+        Side::Inward => polar_arc(inner, start, end),
+        Side::Clockwise => vec![polar_point(inner, start), polar_point(outer, start)],
+        Side::Counterclockwise => vec![polar_point(inner, end), polar_point(outer, end)],
+        side => panic!("{side:?} isn't a side of a polar room"),
+    }
+}
@@ -0,0 +1,134 @@
+//! Working out which rooms can be seen from where, straight from the maze's
+//! layout.
+//!
+//! Walls are as tall as the rooms, so sight lines only matter on the `x`/`z`
+//! plane: a room can be seen from another if some straight line runs from one
+//! to the other through nothing but passages.
+
+use std::collections::HashSet;
+
+use petgraph::{graph::NodeIndex, visit::EdgeRef};
+
+use super::{Disposition, MazeLayout};
+
+/// A passage as seen from one side: its left and right ends, looking through
+/// it.
+type Portal = ((f32, f32), (f32, f32));
+
+/// Every room that could be seen from somewhere in `from`, including `from`
+/// itself.  Sight lines only go as far as `max_passages` passages.
+///
+/// This errs on the side of seeing too much, not too little.  Rooms joined by
+/// a ramp count as seeing everything either one sees.
+pub fn potentially_visible(
+    layout: &MazeLayout,
+    from: NodeIndex,
+    max_passages: usize,
+) -> HashSet<NodeIndex> {
+    let ramp_ends = layout
+        .graph
+        .edges(from)
+        .filter(|edge| {
+            edge.weight().side.is_vertical() && edge.weight().disposition == Disposition::Absent
+        })
+        .map(|edge| {
+            if edge.source() == from {
+                edge.target()
+            } else {
+                edge.source()
+            }
+        });
+    let mut visible = HashSet::new();
+    for room in [from].into_iter().chain(ramp_ends) {
+        visible.insert(room);
+        look_through(
+            layout,
+            &mut vec![room],
+            &mut Vec::new(),
+            max_passages,
+            &mut visible,
+        );
+    }
+    visible
+}
+
+/// Follows every passage out of the last room of `path` that some sight
+/// line along `path` (through `portals`) could also pass through.
+fn look_through(
+    layout: &MazeLayout,
+    path: &mut Vec<NodeIndex>,
+    portals: &mut Vec<Portal>,
+    max_passages: usize,
+    visible: &mut HashSet<NodeIndex>,
+) {
+    if portals.len() >= max_passages {
+        return;
+    }
+    let room = *path.last().unwrap();
+    for edge in layout.graph.edges(room) {
+        let wall = edge.weight();
+        if wall.disposition != Disposition::Absent || wall.side.is_vertical() {
+            continue;
+        }
+        let next = if edge.source() == room {
+            edge.target()
+        } else {
+            edge.source()
+        };
+        if path.contains(&next) {
+            continue;
+        }
+
+        let topology = layout.spec.topology;
+        let (x0, z0) = topology.room_center(layout.graph[room]);
+        let (x1, z1) = topology.room_center(layout.graph[next]);
+        let points = topology.wall_points(wall);
+        let (a, b) = (points[0], points[points.len() - 1]);
+        let portal = if cross((x1 - x0, z1 - z0), (a.0 - x0, a.1 - z0)) > 0.0 {
+            (a, b)
+        } else {
+            (b, a)
+        };
+
+        portals.push(portal);
+        if stabbable(portals) {
+            visible.insert(next);
+            path.push(next);
+            look_through(layout, path, portals, max_passages, visible);
+            path.pop();
+        }
+        portals.pop();
+    }
+}
+
+/// Whether one straight line can pass through every one of `portals`.
+fn stabbable(portals: &[Portal]) -> bool {
+    // Such a line has every left end on one side of it (or on it), and every
+    // right end on the other.  If there's a line like that at all, there's
+    // one through a left end and a right end.
+    const EPSILON: f32 = 1e-4;
+    if portals.len() < 2 {
+        return true;
+    }
+    portals.iter().any(|((left_x, left_z), _)| {
+        portals.iter().any(|(_, (right_x, right_z))| {
+            let along = (right_x - left_x, right_z - left_z);
+            if along.0.hypot(along.1) < EPSILON {
+                return false;
+            }
+            let side = |(x, z): (f32, f32)| cross(along, (x - left_x, z - left_z));
+            let separates = |sign: f32| {
+                portals.iter().all(|(left, right)| {
+                    sign * side(*left) >= -EPSILON && sign * side(*right) <= EPSILON
+                })
+            };
+            separates(1.0) || separates(-1.0)
+        })
+    })
+}
+
+/// The `y` part of the cross product of two vectors on the `x`/`z` plane,
+/// which is positive when `b` turns counterclockwise from `a`.
+fn cross((ax, az): (f32, f32), (bx, bz): (f32, f32)) -> f32 {
+    ax * bz - az * bx
+}
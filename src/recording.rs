@@ -0,0 +1,198 @@
+//! Recordings of runs through a maze, in a compact binary format.
+//!
+//! Every recording starts with a four-byte magic number saying what it
+//! records, then a little-endian `u16` format version and a `u32` count of
+//! the ticks that follow.  Ticks are `FixedUpdate` ticks, [`TICKS_PER_SECOND`]
+//! to a second, and everything in them is little-endian too.  Some kinds of
+//! recording carry more after their ticks.  A new version gets a new number;
+//! old versions keep loading for as long as it's practical.
+
+use std::{error::Error, fmt};
+
+use crate::maze::{LoadError, MazeLayout};
+
+/// How often the avatar moves, and so how many ticks a recording has per
+/// second of the run.
+pub const TICKS_PER_SECOND: u32 = 60;
+
+/// One tick of a [`Ghost`]: where the avatar was, and which way it faced.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GhostFrame {
+    pub position: [f32; 3],
+    /// Radians around the vertical axis.  The avatar never pitches or rolls.
+    pub yaw: f32,
+}
+
+/// The avatar's path through a run, for a ghost to follow on later runs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ghost {
+    pub frames: Vec<GhostFrame>,
+}
+
+/// One tick of the player's input, as `move_avatars` sees it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InputFrame {
+    /// `Avatar::walking`.
+    pub walking: f32,
+    /// `Avatar::turning`.
+    pub turning: f32,
+    /// `AvatarPitch::pitch`.
+    pub pitch: f32,
+}
+
+impl InputFrame {
+    pub fn is_moving(&self) -> bool {
+        self.walking != 0.0 || self.turning != 0.0
+    }
+}
+
+/// Everything the player did in a run, and the maze they did it in, so the
+/// run can be played again exactly.  After the ticks comes the maze, as
+/// JSON, to the end of the recording.
+#[derive(Clone, Debug)]
+pub struct InputReplay {
+    pub layout: MazeLayout,
+    pub frames: Vec<InputFrame>,
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The bytes don't start with the magic number for this kind of
+    /// recording.
+    WrongMagic,
+    /// The recording was made by a newer build.
+    UnsupportedVersion(u16),
+    /// The bytes end before the last tick does.
+    Truncated,
+    /// The maze stored with the recording doesn't load.
+    Layout(LoadError),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::WrongMagic => write!(f, "this isn't the right kind of recording"),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "recording format version {version} isn't supported")
+            }
+            DecodeError::Truncated => write!(f, "the recording is cut short"),
+            DecodeError::Layout(err) => write!(f, "the recording's maze is broken: {err}"),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Layout(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Ghost {
+    const MAGIC: [u8; 4] = *b"MZGH";
+    const VERSION: u16 = 1;
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(Self::MAGIC, Self::VERSION, self.frames.len());
+        for frame in &self.frames {
+            for value in frame.position.into_iter().chain([frame.yaw]) {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(bytes);
+        let count = reader.header(Self::MAGIC, Self::VERSION)?;
+        let mut frames = Vec::new();
+        for _ in 0..count {
+            frames.push(GhostFrame {
+                position: [reader.f32()?, reader.f32()?, reader.f32()?],
+                yaw: reader.f32()?,
+            });
+        }
+        Ok(Ghost { frames })
+    }
+}
+
+impl InputReplay {
+    const MAGIC: [u8; 4] = *b"MZIN";
+    const VERSION: u16 = 1;
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(Self::MAGIC, Self::VERSION, self.frames.len());
+        for frame in &self.frames {
+            for value in [frame.walking, frame.turning, frame.pitch] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        bytes.extend(self.layout.to_json().into_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(bytes);
+        let count = reader.header(Self::MAGIC, Self::VERSION)?;
+        let mut frames = Vec::new();
+        for _ in 0..count {
+            frames.push(InputFrame {
+                walking: reader.f32()?,
+                turning: reader.f32()?,
+                pitch: reader.f32()?,
+            });
+        }
+        let text = String::from_utf8_lossy(reader.0);
+        let layout = MazeLayout::from_json(&text).map_err(DecodeError::Layout)?;
+        Ok(InputReplay { layout, frames })
+    }
+}
+
+/// How many ticks a run with these inputs took, counting from the first tick
+/// the avatar moved on, like the run clock does.
+pub fn run_ticks(frames: &[InputFrame]) -> usize {
+    frames
+        .iter()
+        .position(InputFrame::is_moving)
+        .map_or(0, |first| frames.len() - first)
+}
+
+fn header(magic: [u8; 4], version: u16, count: usize) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    bytes.extend(version.to_le_bytes());
+    bytes.extend((count as u32).to_le_bytes());
+    bytes
+}
+
+/// Reads values off the front of a recording.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        if self.0.len() < N {
+            return Err(DecodeError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(taken.try_into().unwrap())
+    }
+
+    /// Checks the magic number and version, and returns the tick count.
+    /// Versions up to `version` are accepted.
+    fn header(&mut self, magic: [u8; 4], version: u16) -> Result<u32, DecodeError> {
+        if self.take::<4>()? != magic {
+            return Err(DecodeError::WrongMagic);
+        }
+        let found = u16::from_le_bytes(self.take()?);
+        if found == 0 || found > version {
+            return Err(DecodeError::UnsupportedVersion(found));
+        }
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, DecodeError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+}
@@ -0,0 +1,167 @@
+//! The player's best time on each maze, and a ghost and a replay of the run
+//! that set it, kept between sessions.
+
+use bevy::{prelude::*, utils::HashMap};
+use browser_maze_bevy::{
+    maze::{Algorithm, MazeLayout},
+    recording::{Ghost, InputReplay},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+/// Where the best times are stored.
+const BEST_TIMES: &str = "best-times.json";
+
+/// What a best time is kept for.  Two mazes with the same key are the same
+/// maze, as far as a time trial cares.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordKey {
+    pub seed: u64,
+    pub algorithm: Algorithm,
+    /// A hash of the maze's whole spec, which tells apart mazes from the
+    /// same seed and algorithm that differ in anything else: topology,
+    /// size, mask, braid or floors.
+    pub spec: u64,
+}
+
+impl RecordKey {
+    /// The key for a generated maze.  Hand-drawn levels aren't generated
+    /// from a seed, so they should be kept apart by the caller.
+    pub fn of(layout: &MazeLayout) -> Self {
+        let spec = serde_json::to_string(&layout.spec).expect("specs should serialize");
+        RecordKey {
+            seed: layout.spec.seed,
+            algorithm: layout.spec.algorithm,
+            spec: stable_hash(spec.as_bytes()),
+        }
+    }
+
+    /// A name to store things about this maze under, like
+    /// `ghost-00000000deadbeef-wilson-0123456789abcdef.bin`.
+    fn file_name(&self, prefix: &str, extension: &str) -> String {
+        let algorithm: String = self
+            .algorithm
+            .to_string()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        format!(
+            "{prefix}-{:016x}-{}-{:016x}.{extension}",
+            self.seed,
+            algorithm.trim_matches('-'),
+            self.spec,
+        )
+    }
+}
+
+/// A 64-bit FNV-1a hash.  Unlike Rust's own hashers, it's guaranteed to stay
+/// the same from one build to the next, so stored keys keep matching.
+fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The ghost of the best run through `key`'s maze, if it has one.
+pub fn load_ghost(key: &RecordKey) -> Option<Ghost> {
+    let name = key.file_name("ghost", "bin");
+    match Ghost::from_bytes(&storage::load_bytes(&name)?) {
+        Ok(ghost) => Some(ghost),
+        Err(err) => {
+            warn!("Couldn't read the ghost in {name}: {err}");
+            None
+        }
+    }
+}
+
+pub fn save_ghost(key: &RecordKey, ghost: &Ghost) {
+    let name = key.file_name("ghost", "bin");
+    if let Err(err) = storage::save_bytes(&name, &ghost.to_bytes()) {
+        warn!("Couldn't save the ghost to {name}: {err}");
+    }
+}
+
+/// Keeps a replay of the best run through `key`'s maze, so the best time can
+/// be checked by playing it back.
+pub fn save_replay(key: &RecordKey, replay: &InputReplay) {
+    let name = key.file_name("best", "replay");
+    if let Err(err) = storage::save_bytes(&name, &replay.to_bytes()) {
+        warn!("Couldn't save the replay to {name}: {err}");
+    }
+}
+
+/// One line of the stored best times.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    #[serde(flatten)]
+    key: RecordKey,
+    /// `FixedUpdate` ticks, so a replay of the run can be checked against
+    /// it exactly.
+    ticks: u32,
+}
+
+/// The best time on each maze, in `FixedUpdate` ticks.
+#[derive(Default, Resource)]
+pub struct BestTimes(HashMap<RecordKey, u32>);
+
+impl BestTimes {
+    /// Reads the stored best times.  Missing or unreadable records start the
+    /// player over with none.
+    pub fn load() -> Self {
+        let Some(text) = storage::load(BEST_TIMES) else {
+            return BestTimes::default();
+        };
+        match serde_json::from_str::<Vec<Record>>(&text) {
+            Ok(records) => BestTimes(
+                records
+                    .into_iter()
+                    .map(|record| (record.key, record.ticks))
+                    .collect(),
+            ),
+            Err(err) => {
+                warn!("Couldn't read the best times from {BEST_TIMES}: {err}");
+                BestTimes::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let mut records: Vec<Record> = self
+            .0
+            .iter()
+            .map(|(key, ticks)| Record {
+                key: *key,
+                ticks: *ticks,
+            })
+            .collect();
+        // Keep the file stable from one save to the next.
+        records.sort_by_key(|record| {
+            (
+                record.key.seed,
+                record.key.algorithm.to_string(),
+                record.key.spec,
+            )
+        });
+        let text = serde_json::to_string_pretty(&records).expect("records should serialize");
+        if let Err(err) = storage::save(BEST_TIMES, &text) {
+            warn!("Couldn't save the best times to {BEST_TIMES}: {err}");
+        }
+    }
+
+    pub fn get(&self, key: &RecordKey) -> Option<u32> {
+        self.0.get(key).copied()
+    }
+
+    /// Keeps `ticks` if it beats the best time for `key`, and says whether
+    /// it did.
+    pub fn record(&mut self, key: RecordKey, ticks: u32) -> bool {
+        let best = self.0.entry(key).or_insert(u32::MAX);
+        let beaten = ticks < *best;
+        if beaten {
+            *best = ticks;
+        }
+        beaten
+    }
+}
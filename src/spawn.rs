@@ -0,0 +1,749 @@
+//! Turns a [`MazeLayout`] into meshes and colliders.
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
+    utils::{HashMap, HashSet},
+};
+use bevy_rapier3d::prelude::*;
+
+use browser_maze_bevy::maze::{
+    visibility::potentially_visible, Disposition, MazeLayout, Ramp, Room, Topology, Wall,
+};
+use petgraph::graph::NodeIndex;
+
+#[derive(Debug)]
+pub struct Sizes {
+    pub room_side_length: f32,
+    pub wall_radius: f32,
+    pub room_height: f32,
+    /// How thick the floor between two floors of the maze is.
+    pub floor_thickness: f32,
+    pub ramp_width: f32,
+}
+
+impl Sizes {
+    /// Scales a position from the maze's room units to world space, at floor
+    /// level.
+    pub fn to_world(&self, (x, z): (f32, f32)) -> Vec3 {
+        Vec3::new(x, 0.0, z) * self.room_side_length
+    }
+
+    /// The distance from one floor of the maze to the next.
+    pub fn floor_height(&self) -> f32 {
+        self.room_height + self.floor_thickness
+    }
+
+    /// The floor nearest to the world-space height `y`.
+    pub fn floor_containing(&self, y: f32) -> i32 {
+        (y / self.floor_height()).round() as i32
+    }
+
+    /// The world-space position of the middle of `room`, at floor level.
+    pub fn room_center(&self, layout: &MazeLayout, room: Room) -> Vec3 {
+        self.to_world(layout.spec.topology.room_center(room))
+            + Vec3::Y * room.floor as f32 * self.floor_height()
+    }
+}
+
+/// How to spawn a maze's walls and corner posts.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub enum WallBatching {
+    /// One entity (and one collider) per wall segment and per corner post.
+    /// Easy to pick apart in an inspector, but it adds up to well over a
+    /// thousand entities and draw calls for a 20×20 maze.  Culling hides
+    /// each wall on its own.
+    Separate,
+    /// One mesh and one compound collider for the walls and corner posts of
+    /// every `BATCH_SIZE`-room square.  Far fewer draw calls, and culling
+    /// still hides each batch that none of its rooms can be seen from.
+    #[default]
+    Batched,
+}
+
+impl WallBatching {
+    pub fn name(self) -> &'static str {
+        match self {
+            WallBatching::Separate => "Separate",
+            WallBatching::Batched => "Batched",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            WallBatching::Separate => WallBatching::Batched,
+            WallBatching::Batched => WallBatching::Separate,
+        }
+    }
+}
+
+/// How many room units wide and deep each chunk of the maze is.
+const CHUNK_SIZE: f32 = 16.0;
+
+/// One square piece of one floor of the maze, which gets spawned and
+/// despawned all at once.  Also marks the entity holding that piece.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Component)]
+pub struct Chunk {
+    pub floor: i32,
+    // Counts chunks along `x`.
+    pub x: i32,
+    // Counts chunks along `z`.
+    pub z: i32,
+}
+
+impl Chunk {
+    /// The chunk of `floor` that a point (in room units) falls in.
+    fn containing(floor: i32, (x, z): (f32, f32)) -> Self {
+        Chunk {
+            floor,
+            x: (x / CHUNK_SIZE).floor() as i32,
+            z: (z / CHUNK_SIZE).floor() as i32,
+        }
+    }
+}
+
+/// The parts of the maze that make up one chunk.  Each part goes in whichever
+/// chunk its middle falls in.
+#[derive(Default)]
+struct ChunkContents {
+    /// The rooms to build floors under.  This leaves out rooms that ramps
+    /// climb into.
+    rooms: Vec<Room>,
+    /// The present walls along the floor.
+    walls: Vec<Wall>,
+    /// Each corner, with the rooms that meet there.
+    corners: Vec<((f32, f32), Vec<Room>)>,
+    ramps: Vec<Ramp>,
+}
+
+/// The contents of the chunk of `floor` that `point` falls in, starting
+/// from empty if there are none yet.
+fn chunk_at(
+    chunks: &mut HashMap<Chunk, ChunkContents>,
+    floor: i32,
+    point: (f32, f32),
+) -> &mut ChunkContents {
+    chunks.entry(Chunk::containing(floor, point)).or_default()
+}
+
+/// Marks an entity that can only be seen from where `0`'s rooms can be seen.
+/// Culling hides it when none of them are in sight.  A wall or corner post
+/// spawned with `WallBatching::Separate` has the one or few rooms it touches;
+/// a batch has every room its walls and posts touch.
+#[derive(Clone, Debug, Component)]
+pub struct Cullable(pub Vec<Room>);
+
+/// A maze, sorted into chunks so that it can be spawned a piece at a time.
+/// Large mazes have far too many walls to spawn all at once.
+#[derive(Resource)]
+pub struct ChunkedMaze {
+    pub layout: MazeLayout,
+    ids_by_room: HashMap<Room, NodeIndex>,
+    chunks: HashMap<Chunk, ChunkContents>,
+    /// The corners of the box (in world space, along `x` and `z`) that the
+    /// whole maze fits in.
+    bounds: (Vec2, Vec2),
+    batching: WallBatching,
+    wall_material: Handle<StandardMaterial>,
+    floor_material: Handle<StandardMaterial>,
+    separate_walls: SeparateWalls,
+}
+
+impl ChunkedMaze {
+    pub fn new(
+        layout: MazeLayout,
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+        sizes: &Sizes,
+        batching: WallBatching,
+    ) -> Self {
+        let topology = layout.spec.topology;
+        let mut chunks = HashMap::<Chunk, ChunkContents>::new();
+
+        let ramps = layout.ramps();
+        // Ramps climb through where the upper room's floor would be.
+        let holes = ramps.iter().map(|ramp| ramp.upper).collect::<HashSet<_>>();
+        for room in layout.graph.node_weights() {
+            if !holes.contains(room) {
+                chunk_at(&mut chunks, room.floor, topology.room_center(*room))
+                    .rooms
+                    .push(*room);
+            }
+        }
+        let walls = layout
+            .walls()
+            .filter(|w| w.disposition == Disposition::Present && !w.side.is_vertical());
+        for wall in walls {
+            chunk_at(&mut chunks, wall.room.floor, topology.wall_midpoint(&wall))
+                .walls
+                .push(wall);
+        }
+        let mut bounds = (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY));
+        for floor in layout.floors() {
+            for (corner, rooms) in layout.corners_with_rooms(floor) {
+                let point = Vec2::new(corner.0, corner.1) * sizes.room_side_length;
+                bounds = (bounds.0.min(point), bounds.1.max(point));
+                chunk_at(&mut chunks, floor, corner)
+                    .corners
+                    .push((corner, rooms));
+            }
+        }
+        if bounds.0.x > bounds.1.x {
+            // Only a lone polar center room has no corners.
+            bounds = (
+                Vec2::splat(-sizes.room_side_length),
+                Vec2::splat(sizes.room_side_length),
+            );
+        }
+        for ramp in ramps {
+            chunk_at(&mut chunks, ramp.lower.floor, ramp.bottom)
+                .ramps
+                .push(ramp);
+        }
+
+        ChunkedMaze {
+            ids_by_room: layout
+                .graph
+                .node_indices()
+                .map(|room| (layout.graph[room], room))
+                .collect(),
+            layout,
+            chunks,
+            bounds,
+            batching,
+            wall_material: materials.add(Color::BLUE.into()),
+            floor_material: materials.add(Color::SILVER.into()),
+            separate_walls: SeparateWalls::default(),
+        }
+    }
+
+    /// The smallest and largest world-space `x` and `z` of any part of the
+    /// maze.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        self.bounds
+    }
+
+    /// The room at the world-space `point`, if that's part of the maze.
+    pub fn room_containing(&self, point: Vec3, sizes: &Sizes) -> Option<Room> {
+        let room = self.layout.spec.topology.room_containing(
+            (
+                point.x / sizes.room_side_length,
+                point.z / sizes.room_side_length,
+            ),
+            sizes.floor_containing(point.y),
+        );
+        self.ids_by_room.contains_key(&room).then_some(room)
+    }
+
+    /// Every room that might be seen from somewhere in `room`, no more than
+    /// `max_passages` passages away.
+    pub fn in_sight(&self, room: Room, max_passages: usize) -> HashSet<Room> {
+        let Some(&room) = self.ids_by_room.get(&room) else {
+            return HashSet::new();
+        };
+        potentially_visible(&self.layout, room, max_passages)
+            .into_iter()
+            .map(|room| self.layout.graph[room])
+            .collect()
+    }
+
+    /// Every chunk, on any floor, with part of the maze no more than
+    /// `distance` from `point` along `x` and `z` (in world space).
+    pub fn chunks_near(&self, point: Vec3, distance: f32, sizes: &Sizes) -> HashSet<Chunk> {
+        let (point, distance) = (
+            point / sizes.room_side_length,
+            distance / sizes.room_side_length,
+        );
+        let min = Chunk::containing(0, (point.x - distance, point.z - distance));
+        let max = Chunk::containing(0, (point.x + distance, point.z + distance));
+        self.layout
+            .floors()
+            .flat_map(|floor| {
+                (min.x..=max.x)
+                    .flat_map(move |x| (min.z..=max.z).map(move |z| Chunk { floor, x, z }))
+            })
+            .filter(|chunk| self.chunks.contains_key(chunk))
+            .collect()
+    }
+
+    /// Spawns the floor, walls, corner posts and ramps of one chunk, under an
+    /// entity marked with the `Chunk`.
+    pub fn build_chunk(
+        &mut self,
+        commands: &mut ChildBuilder,
+        meshes: &mut ResMut<Assets<Mesh>>,
+        sizes: &Sizes,
+        chunk: Chunk,
+    ) {
+        let Some(contents) = self.chunks.get(&chunk) else {
+            return;
+        };
+        let topology = self.layout.spec.topology;
+        commands
+            .spawn((
+                chunk,
+                SpatialBundle::from_transform(Transform::from_xyz(
+                    0.0,
+                    chunk.floor as f32 * sizes.floor_height(),
+                    0.0,
+                )),
+            ))
+            .with_children(|commands| {
+                build_floor(
+                    commands,
+                    meshes,
+                    &self.floor_material,
+                    topology,
+                    sizes,
+                    &contents.rooms,
+                );
+
+                let segments = wall_segments(topology, sizes, &contents.walls, |room| {
+                    self.ids_by_room.contains_key(room)
+                });
+                let corners = contents
+                    .corners
+                    .iter()
+                    .map(|(corner, rooms)| CornerPost {
+                        position: sizes.to_world(*corner),
+                        rooms: rooms.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                match self.batching {
+                    WallBatching::Separate => self.separate_walls.build(
+                        commands,
+                        meshes,
+                        &self.wall_material,
+                        sizes,
+                        &segments,
+                        &corners,
+                    ),
+                    WallBatching::Batched => build_batched_walls(
+                        commands,
+                        meshes,
+                        &self.wall_material,
+                        sizes,
+                        &segments,
+                        &corners,
+                    ),
+                }
+
+                for ramp in &contents.ramps {
+                    build_ramp(commands, meshes, &self.floor_material, ramp, sizes);
+                }
+            });
+    }
+}
+
+/// A straight stretch of wall, centered on `transform` and running along
+/// its `x` axis.
+struct WallSegment {
+    transform: Transform,
+    length: f32,
+    /// The rooms on either side that are part of the maze.  Border walls
+    /// have only the one.
+    rooms: Vec<Room>,
+}
+
+struct CornerPost {
+    position: Vec3,
+    /// The rooms that meet at the post.
+    rooms: Vec<Room>,
+}
+
+/// Every straight stretch of `walls`.  `in_maze` tells the rooms of the maze
+/// from the ones past its border.
+fn wall_segments(
+    topology: Topology,
+    sizes: &Sizes,
+    walls: &[Wall],
+    in_maze: impl Fn(&Room) -> bool,
+) -> Vec<WallSegment> {
+    walls
+        .iter()
+        .flat_map(|wall| {
+            // Curved walls come as several points; build a straight box
+            // between each consecutive pair.
+            let points = topology.wall_points(wall);
+            let rooms = [wall.room, topology.neighbor(wall.room, wall.side)]
+                .into_iter()
+                .filter(&in_maze)
+                .collect::<Vec<_>>();
+            (1..points.len())
+                .map(|i| (points[i - 1], points[i], rooms.clone()))
+                .collect::<Vec<_>>()
+        })
+        .map(|(start, end, rooms)| {
+            let (start, end) = (sizes.to_world(start), sizes.to_world(end));
+            let along = end - start;
+            WallSegment {
+                transform: Transform::from_translation((start + end) / 2.0)
+                    .with_rotation(Quat::from_rotation_y(f32::atan2(-along.z, along.x))),
+                length: along.length(),
+                rooms,
+            }
+        })
+        .collect()
+}
+
+/// A wall segment's box, with its origin at the middle of its *bottom*.
+fn wall_mesh(sizes: &Sizes, length: f32) -> Mesh {
+    shape::Box {
+        min_x: -length / 2.0,
+        max_x: length / 2.0,
+        min_y: 0.0,
+        max_y: sizes.room_height,
+        min_z: -sizes.wall_radius,
+        max_z: sizes.wall_radius,
+    }
+    .into()
+}
+
+/// A corner post, with its origin in the middle.
+fn corner_mesh(sizes: &Sizes) -> Mesh {
+    shape::Cylinder {
+        radius: sizes.wall_radius,
+        height: sizes.room_height,
+        resolution: 8,
+        segments: 1,
+    }
+    .into()
+}
+
+/// The meshes shared between chunks by `WallBatching::Separate`.
+#[derive(Default)]
+struct SeparateWalls {
+    corner_mesh: Option<Handle<Mesh>>,
+    // Wall segments come in only a handful of lengths, so share a mesh per
+    // length (rounded to the millimeter).
+    wall_meshes: HashMap<u32, Handle<Mesh>>,
+}
+
+impl SeparateWalls {
+    fn build(
+        &mut self,
+        commands: &mut ChildBuilder,
+        meshes: &mut ResMut<Assets<Mesh>>,
+        material: &Handle<StandardMaterial>,
+        sizes: &Sizes,
+        segments: &[WallSegment],
+        corners: &[CornerPost],
+    ) {
+        for segment in segments {
+            let wall_mesh = self
+                .wall_meshes
+                .entry((segment.length * 1000.0).round() as u32)
+                .or_insert_with(|| meshes.add(wall_mesh(sizes, segment.length)));
+
+            commands
+                .spawn((
+                    PbrBundle {
+                        mesh: wall_mesh.clone(),
+                        material: material.clone(),
+                        transform: segment.transform,
+                        ..default()
+                    },
+                    Cullable(segment.rooms.clone()),
+                ))
+                .with_children(|commands| {
+                    // The collider `cuboid` primitive is always *centered* at the origin,
+                    // but the mesh above puts the origin at the *bottom* of the wall.
+                    commands.spawn((
+                        Collider::cuboid(
+                            segment.length / 2.0,
+                            sizes.room_height / 2.0,
+                            sizes.wall_radius,
+                        ),
+                        SpatialBundle {
+                            transform: Transform::from_translation(
+                                Vec3::Y * sizes.room_height / 2.0,
+                            ),
+                            ..default()
+                        },
+                    ));
+                });
+        }
+
+        // Spawn corner columns
+        let corner_mesh = self
+            .corner_mesh
+            .get_or_insert_with(|| meshes.add(corner_mesh(sizes)));
+        for corner in corners {
+            commands.spawn((
+                PbrBundle {
+                    mesh: corner_mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(
+                        corner.position + Vec3::Y * sizes.room_height / 2.0,
+                    ),
+                    ..default()
+                },
+                Collider::cylinder(sizes.room_height / 2.0, sizes.wall_radius),
+                Cullable(corner.rooms.clone()),
+            ));
+        }
+    }
+}
+
+/// How many room units wide and deep each batch of walls is.  Smaller
+/// batches cost more draw calls, but give culling more to hide.
+const BATCH_SIZE: f32 = 4.0;
+
+/// The walls and corner posts of one batch, and everything they touch.
+#[derive(Default)]
+struct WallBatch {
+    mesh: MeshBatch,
+    shapes: Vec<(Vec3, Quat, Collider)>,
+    rooms: HashSet<Room>,
+}
+
+/// Builds the wall segments and corner posts a batch at a time, each batch
+/// one entity with one mesh and one compound collider.  Each batch covers a
+/// few rooms, and is culled once none of the rooms its walls touch are in
+/// sight.
+fn build_batched_walls(
+    commands: &mut ChildBuilder,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    material: &Handle<StandardMaterial>,
+    sizes: &Sizes,
+    segments: &[WallSegment],
+    corners: &[CornerPost],
+) {
+    let mut batches = HashMap::<(i32, i32), WallBatch>::new();
+    // Batches tile the floor like chunks do, only smaller.
+    let cell = |position: Vec3| {
+        let cell = position / (sizes.room_side_length * BATCH_SIZE);
+        (cell.x.floor() as i32, cell.z.floor() as i32)
+    };
+    // As with separate walls, the wall meshes sit on the origin but the
+    // colliders are centered on it.
+    let half_up = Vec3::Y * sizes.room_height / 2.0;
+    for segment in segments {
+        let batch = batches
+            .entry(cell(segment.transform.translation))
+            .or_default();
+        batch
+            .mesh
+            .add(&wall_mesh(sizes, segment.length), segment.transform);
+        batch.shapes.push((
+            segment.transform.translation + half_up,
+            segment.transform.rotation,
+            Collider::cuboid(
+                segment.length / 2.0,
+                sizes.room_height / 2.0,
+                sizes.wall_radius,
+            ),
+        ));
+        batch.rooms.extend(&segment.rooms);
+    }
+    let corner_mesh = corner_mesh(sizes);
+    for corner in corners {
+        let batch = batches.entry(cell(corner.position)).or_default();
+        let position = corner.position + half_up;
+        batch
+            .mesh
+            .add(&corner_mesh, Transform::from_translation(position));
+        batch.shapes.push((
+            position,
+            Quat::IDENTITY,
+            Collider::cylinder(sizes.room_height / 2.0, sizes.wall_radius),
+        ));
+        batch.rooms.extend(&corner.rooms);
+    }
+
+    for batch in batches.into_values() {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(batch.mesh.into_mesh()),
+                material: material.clone(),
+                ..default()
+            },
+            Collider::compound(batch.shapes),
+            Cullable(batch.rooms.into_iter().collect()),
+        ));
+    }
+}
+
+/// Several meshes merged into one, so they take a single draw call.
+#[derive(Default)]
+struct MeshBatch {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+impl MeshBatch {
+    /// Adds a copy of `mesh`, moved into place by `transform`.  The mesh
+    /// needs positions, normals, UVs and indices, like Bevy's built-in shapes
+    /// have.
+    fn add(&mut self, mesh: &Mesh, transform: Transform) {
+        let first = self.positions.len() as u32;
+        let positions = mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|values| values.as_float3())
+            .expect("batched meshes need positions");
+        let normals = mesh
+            .attribute(Mesh::ATTRIBUTE_NORMAL)
+            .and_then(|values| values.as_float3())
+            .expect("batched meshes need normals");
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            panic!("batched meshes need UVs");
+        };
+        let indices = mesh.indices().expect("batched meshes need indices");
+
+        self.positions.extend(
+            positions
+                .iter()
+                .map(|p| transform.transform_point(Vec3::from(*p)).to_array()),
+        );
+        self.normals.extend(
+            normals
+                .iter()
+                .map(|n| (transform.rotation * Vec3::from(*n)).to_array()),
+        );
+        self.uvs.extend(uvs);
+        self.indices
+            .extend(indices.iter().map(|index| first + index as u32));
+    }
+
+    fn into_mesh(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        mesh
+    }
+}
+
+/// Builds the floor under `rooms` as one mesh and one collider.
+fn build_floor(
+    commands: &mut ChildBuilder,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    material: &Handle<StandardMaterial>,
+    topology: Topology,
+    sizes: &Sizes,
+    rooms: &[Room],
+) {
+    let mut top = Vec::<Vec3>::new();
+    let mut triangles = Vec::<[u32; 3]>::new();
+    for room in rooms {
+        // Fan out from the middle of the room.  The outline is
+        // counterclockwise on the `x`/`z` plane, which is clockwise seen from
+        // above, so flip each triangle to face up.
+        let center = top.len() as u32;
+        top.push(sizes.to_world(topology.room_center(*room)));
+        let outline = topology.room_outline(*room);
+        let first = top.len() as u32;
+        top.extend(outline.iter().map(|point| sizes.to_world(*point)));
+        let count = outline.len() as u32;
+        triangles.extend((0..count).map(|i| [center, first + (i + 1) % count, first + i]));
+    }
+    if triangles.is_empty() {
+        return;
+    }
+
+    // The underside is the ceiling of the floor below.
+    let below = Vec3::NEG_Y * sizes.floor_thickness;
+    let mut positions = top.clone();
+    positions.extend(top.iter().map(|point| *point + below));
+    let mut normals = vec![[0.0, 1.0, 0.0]; top.len()];
+    normals.extend(vec![[0.0, -1.0, 0.0]; top.len()]);
+    let uvs = positions
+        .iter()
+        .map(|point| {
+            [
+                point.x / sizes.room_side_length,
+                point.z / sizes.room_side_length,
+            ]
+        })
+        .collect::<Vec<_>>();
+    let underside = top.len() as u32;
+    let indices = triangles
+        .iter()
+        .flat_map(|[a, b, c]| [*a, *b, *c])
+        .chain(
+            triangles
+                .iter()
+                .flat_map(|[a, b, c]| [underside + a, underside + c, underside + b]),
+        )
+        .collect::<Vec<_>>();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        positions.iter().map(|p| p.to_array()).collect::<Vec<_>>(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(mesh),
+            material: material.clone(),
+            ..default()
+        },
+        Collider::trimesh(top, triangles),
+    ));
+}
+
+/// Builds a ramp up from `ramp.lower`'s floor, which is at the origin of
+/// `commands`' parent.
+fn build_ramp(
+    commands: &mut ChildBuilder,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    material: &Handle<StandardMaterial>,
+    ramp: &Ramp,
+    sizes: &Sizes,
+) {
+    let (bottom, top) = (sizes.to_world(ramp.bottom), sizes.to_world(ramp.top));
+    let along = top - bottom;
+    let rise = sizes.floor_height();
+    let length = along.length().hypot(rise);
+    // Yaw to face from bottom to top, then pitch up to meet the next floor.
+    let transform = Transform::from_translation((bottom + top) / 2.0 + Vec3::Y * rise / 2.0)
+        .with_rotation(
+            Quat::from_rotation_y(f32::atan2(-along.z, along.x))
+                * Quat::from_rotation_z(f32::atan2(rise, along.length())),
+        );
+
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(
+                shape::Box {
+                    min_x: -length / 2.0,
+                    max_x: length / 2.0,
+                    min_y: -sizes.floor_thickness,
+                    max_y: 0.0,
+                    min_z: -sizes.ramp_width / 2.0,
+                    max_z: sizes.ramp_width / 2.0,
+                }
+                .into(),
+            ),
+            material: material.clone(),
+            transform,
+            ..default()
+        })
+        .with_children(|commands| {
+            // As with walls, the mesh keeps its top surface on the origin,
+            // but the collider is centered there.
+            commands.spawn((
+                Collider::cuboid(
+                    length / 2.0,
+                    sizes.floor_thickness / 2.0,
+                    sizes.ramp_width / 2.0,
+                ),
+                SpatialBundle {
+                    transform: Transform::from_translation(
+                        Vec3::NEG_Y * sizes.floor_thickness / 2.0,
+                    ),
+                    ..default()
+                },
+            ));
+        });
+}
@@ -0,0 +1,65 @@
+//! Somewhere to keep things between sessions: files in the working directory
+//! on native builds, and `localStorage` in the browser.
+
+/// Reads what was last stored under `name`, if anything.
+pub fn load(name: &str) -> Option<String> {
+    #[cfg(target_family = "wasm")]
+    {
+        local_storage()?.get_item(&key(name)).ok().flatten()
+    }
+    #[cfg(not(target_family = "wasm"))]
+    {
+        std::fs::read_to_string(name).ok()
+    }
+}
+
+/// Stores `contents` under `name`, replacing whatever was there.
+pub fn save(name: &str, contents: &str) -> Result<(), String> {
+    #[cfg(target_family = "wasm")]
+    {
+        local_storage()
+            .ok_or_else(|| "localStorage isn't available".to_owned())?
+            .set_item(&key(name), contents)
+            .map_err(|err| format!("{err:?}"))
+    }
+    #[cfg(not(target_family = "wasm"))]
+    {
+        std::fs::write(name, contents).map_err(|err| err.to_string())
+    }
+}
+
+/// Reads binary contents stored with [`save_bytes`].
+pub fn load_bytes(name: &str) -> Option<Vec<u8>> {
+    #[cfg(target_family = "wasm")]
+    {
+        // `localStorage` only holds strings.
+        base64::decode(load(name)?).ok()
+    }
+    #[cfg(not(target_family = "wasm"))]
+    {
+        std::fs::read(name).ok()
+    }
+}
+
+pub fn save_bytes(name: &str, contents: &[u8]) -> Result<(), String> {
+    #[cfg(target_family = "wasm")]
+    {
+        save(name, &base64::encode(contents))
+    }
+    #[cfg(not(target_family = "wasm"))]
+    {
+        std::fs::write(name, contents).map_err(|err| err.to_string())
+    }
+}
+
+/// `localStorage` is shared by everything served from the same origin, so
+/// keys get a prefix.
+#[cfg(target_family = "wasm")]
+fn key(name: &str) -> String {
+    format!("browser-maze-bevy/{name}")
+}
+
+#[cfg(target_family = "wasm")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
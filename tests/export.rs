@@ -0,0 +1,72 @@
+//! Pictures of mazes should come out the same every time, at the size they
+//! ask for.
+//!
+//! To accept a new SVG, run `UPDATE_GOLDEN=1 cargo test --test export` and
+//! review the diff.
+
+use std::{fs, path::PathBuf};
+
+use browser_maze_bevy::maze::{
+    Algorithm, ExportOptions, MazeLayout, MazeSpec, Topology, MAX_PNG_PIXELS,
+};
+
+fn layout(rooms: i32) -> MazeLayout {
+    MazeLayout::generate(&MazeSpec {
+        topology: Topology::Square,
+        x_range: 0..=rooms,
+        z_range: 0..=rooms,
+        mask: None,
+        floors: 1,
+        algorithm: Algorithm::RecursiveBacktracker,
+        braid: 0.0,
+        seed: 0x5eed_f00d,
+    })
+    .unwrap()
+}
+
+#[test]
+fn svg_matches_golden() {
+    let options = ExportOptions {
+        show_solution: true,
+        ..ExportOptions::default()
+    };
+    let svg = layout(3).to_svg(&options);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/square-3x3.svg");
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &svg).unwrap();
+    }
+    assert_eq!(
+        svg,
+        fs::read_to_string(&path).unwrap(),
+        "the SVG no longer matches {}",
+        path.display()
+    );
+}
+
+#[test]
+fn png_decodes_at_its_size() {
+    let options = ExportOptions {
+        pixels_per_room: 10.0,
+        show_solution: true,
+    };
+    let png = layout(3).to_png(&options).unwrap();
+    let decoder = png::Decoder::new(png.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut pixels).unwrap();
+    // Three rooms and half a room of margin on each side, both ways.
+    assert_eq!((frame.width, frame.height), (40, 40));
+    assert_eq!(frame.color_type, png::ColorType::Rgb);
+    assert_eq!(frame.bit_depth, png::BitDepth::Eight);
+    // The corners are margin, so they're background.
+    assert_eq!(pixels[..3], [0xff, 0xff, 0xff]);
+}
+
+#[test]
+fn png_refuses_absurd_sizes() {
+    let options = ExportOptions {
+        pixels_per_room: (MAX_PNG_PIXELS as f32).sqrt(),
+        show_solution: false,
+    };
+    assert_eq!(layout(3).to_png(&options), None);
+}
@@ -0,0 +1,176 @@
+//! Mazes generated from fixed seeds, checked against drawings saved in
+//! `tests/golden/`.  A seed someone shares should give the same maze in every
+//! release, so these drawings only change when generation changes on purpose.
+//!
+//! To accept new output, run `UPDATE_GOLDEN=1 cargo test --test golden` and
+//! review the diff.
+
+use std::{fmt::Write, fs, path::PathBuf};
+
+use browser_maze_bevy::maze::{
+    Algorithm, Disposition, Mask, MazeAlgorithm, MazeLayout, MazeSpec, Room, Selection, Topology,
+};
+
+const SEED: u64 = 0x5eed_f00d;
+
+fn spec(topology: Topology, algorithm: Algorithm) -> MazeSpec {
+    MazeSpec {
+        topology,
+        x_range: -5..=5,
+        z_range: match topology {
+            Topology::Polar => 0..=5,
+            _ => -4..=4,
+        },
+        mask: None,
+        floors: 1,
+        algorithm,
+        braid: 0.0,
+        seed: SEED,
+    }
+}
+
+/// How names are written in file names: lowercase, with dashes for spaces.
+fn slug(name: &str) -> String {
+    name.to_lowercase().replace(' ', "-")
+}
+
+/// The maze in text: drawn, for square mazes, and otherwise listed wall by
+/// wall.
+fn draw(layout: &MazeLayout) -> String {
+    if let Some(drawing) = layout.to_ascii() {
+        return drawing;
+    }
+    let room = |room: Room| format!("({}, {}, {})", room.column, room.row, room.floor);
+    let mut text = format!("start {}\ngoal {}\n", room(layout.start), room(layout.goal));
+    for wall in layout.walls() {
+        if wall.disposition == Disposition::Absent {
+            writeln!(text, "open {} {:?}", room(wall.room), wall.side).unwrap();
+        }
+    }
+    // A mask decides where the border runs, so check that too.
+    if layout.spec.mask.is_some() {
+        for wall in &layout.border {
+            writeln!(text, "border {} {:?}", room(wall.room), wall.side).unwrap();
+        }
+    }
+    text
+}
+
+/// Checks every case against its golden file, and reports all the
+/// mismatches at once.
+fn check(cases: Vec<(String, MazeSpec)>) {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut mismatches = Vec::new();
+    for (name, spec) in cases {
+        let path = directory.join(format!("{name}.txt"));
+        let drawing = draw(&MazeLayout::generate(&spec).unwrap());
+        if update {
+            fs::create_dir_all(&directory).unwrap();
+            fs::write(&path, &drawing).unwrap();
+        } else if fs::read_to_string(&path).ok().as_ref() != Some(&drawing) {
+            mismatches.push(format!("{name}:\n{drawing}"));
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "these mazes no longer match tests/golden/:\n\n{}",
+        mismatches.join("\n")
+    );
+}
+
+/// Every algorithm that can generate `topology`'s mazes.
+fn every_algorithm(topology: Topology) -> Vec<(String, MazeSpec)> {
+    Algorithm::ALL
+        .into_iter()
+        .map(|algorithm| spec(topology, algorithm))
+        .filter(|spec| spec.algorithm.supports(spec))
+        .map(|spec| {
+            let name = format!("{}-{}", slug(topology.name()), slug(spec.algorithm.name()));
+            (name, spec)
+        })
+        .collect()
+}
+
+#[test]
+fn square() {
+    check(every_algorithm(Topology::Square));
+}
+
+#[test]
+fn hex() {
+    check(every_algorithm(Topology::Hex));
+}
+
+#[test]
+fn polar() {
+    check(every_algorithm(Topology::Polar));
+}
+
+#[test]
+fn growing_tree_selections() {
+    check(
+        [("newest", Selection::NEWEST), ("oldest", Selection::OLDEST)]
+            .into_iter()
+            .map(|(name, selection)| {
+                let algorithm = Algorithm::GrowingTree(selection);
+                (
+                    format!("square-growing-tree-{name}"),
+                    spec(Topology::Square, algorithm),
+                )
+            })
+            .collect(),
+    );
+}
+
+#[test]
+fn options() {
+    let heart = Mask::parse(include_bytes!("../assets/masks/heart.txt")).unwrap();
+    check(vec![
+        (
+            "square-braided".to_owned(),
+            MazeSpec {
+                braid: 0.5,
+                ..spec(Topology::Square, Algorithm::default())
+            },
+        ),
+        (
+            "polar-braided".to_owned(),
+            MazeSpec {
+                braid: 1.0,
+                ..spec(Topology::Polar, Algorithm::default())
+            },
+        ),
+        (
+            "square-three-floors".to_owned(),
+            MazeSpec {
+                floors: 3,
+                ..spec(Topology::Square, Algorithm::default())
+            },
+        ),
+        (
+            "hex-two-floors".to_owned(),
+            MazeSpec {
+                floors: 2,
+                ..spec(Topology::Hex, Algorithm::default())
+            },
+        ),
+        (
+            "square-heart".to_owned(),
+            MazeSpec {
+                mask: Some(heart.clone()),
+                x_range: -10..=10,
+                z_range: -10..=10,
+                ..spec(Topology::Square, Algorithm::Wilson)
+            },
+        ),
+        (
+            "polar-heart".to_owned(),
+            MazeSpec {
+                mask: Some(heart),
+                z_range: 0..=8,
+                ..spec(Topology::Polar, Algorithm::Wilson)
+            },
+        ),
+    ]);
+}
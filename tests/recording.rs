@@ -0,0 +1,86 @@
+//! Recordings should load back exactly as they were saved, and say why when
+//! they can't.
+
+use browser_maze_bevy::{
+    maze::{Algorithm, MazeLayout, MazeSpec, Topology},
+    recording::{DecodeError, Ghost, GhostFrame, InputFrame, InputReplay},
+};
+
+fn ghost() -> Ghost {
+    Ghost {
+        frames: (0..100)
+            .map(|tick| GhostFrame {
+                position: [tick as f32 * 0.1, 0.0, -(tick as f32) * 0.05],
+                yaw: tick as f32 * 0.01,
+            })
+            .collect(),
+    }
+}
+
+fn input_replay() -> InputReplay {
+    let spec = MazeSpec {
+        topology: Topology::Square,
+        x_range: -3..=3,
+        z_range: -3..=3,
+        mask: None,
+        floors: 1,
+        algorithm: Algorithm::default(),
+        braid: 0.0,
+        seed: 0x5eed_f00d,
+    };
+    InputReplay {
+        layout: MazeLayout::generate(&spec).unwrap(),
+        frames: (0..100)
+            .map(|tick| InputFrame {
+                walking: if tick < 10 { 0.0 } else { 1.0 },
+                turning: (tick % 3) as f32 - 1.0,
+                pitch: tick as f32 * -0.001,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn ghost_round_trips() {
+    let ghost = ghost();
+    assert_eq!(Ghost::from_bytes(&ghost.to_bytes()).unwrap(), ghost);
+}
+
+#[test]
+fn ghost_rejects_bad_recordings() {
+    let bytes = ghost().to_bytes();
+    assert!(matches!(
+        Ghost::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(DecodeError::Truncated)
+    ));
+
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] = b'X';
+    assert!(matches!(
+        Ghost::from_bytes(&wrong_magic),
+        Err(DecodeError::WrongMagic)
+    ));
+
+    let mut too_new = bytes;
+    too_new[4..6].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert!(matches!(
+        Ghost::from_bytes(&too_new),
+        Err(DecodeError::UnsupportedVersion(u16::MAX))
+    ));
+}
+
+#[test]
+fn input_replay_round_trips() {
+    let replay = input_replay();
+    let loaded = InputReplay::from_bytes(&replay.to_bytes()).unwrap();
+    assert_eq!(loaded.frames, replay.frames);
+    assert_eq!(loaded.layout.to_json(), replay.layout.to_json());
+}
+
+#[test]
+fn input_replay_rejects_ghosts() {
+    assert!(matches!(
+        InputReplay::from_bytes(&ghost().to_bytes()),
+        Err(DecodeError::WrongMagic)
+    ));
+}